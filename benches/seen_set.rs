@@ -0,0 +1,97 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use std::collections::HashSet;
+use std::hint::black_box;
+use std::sync::Arc;
+use std::thread;
+use tokio::sync::Mutex;
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/seen_set.rs"]
+mod seen_set;
+
+use seen_set::SeenSet;
+
+/// IDs already in the set, about a year of postings across many profiles.
+const SEEN: usize = 200_000;
+/// Polls landing at once, each with a result set of `RESULT_SET` jobs.
+const POLLS: usize = 4;
+const RESULT_SET: usize = 50;
+const ROUNDS: usize = 100;
+
+fn ids(prefix: &str, count: usize) -> impl Iterator<Item = String> + '_ {
+    (0..count).map(move |i| format!("{}-{:08}", prefix, i))
+}
+
+/// The old store: one `tokio::sync::Mutex` around a `HashSet`, taken per job.
+fn insert_global(c: &mut Criterion) {
+    c.bench_function("insert/global_mutex_per_job", |b| {
+        b.iter_batched(
+            || Arc::new(Mutex::new(ids("seen", SEEN).collect::<HashSet<_>>())),
+            |set| {
+                let polls: Vec<_> = (0..POLLS)
+                    .map(|poll| {
+                        let set = set.clone();
+                        thread::spawn(move || {
+                            for id in ids(&format!("poll{}", poll), RESULT_SET * ROUNDS) {
+                                black_box(set.blocking_lock().insert(id));
+                            }
+                        })
+                    })
+                    .collect();
+                polls.into_iter().for_each(|poll| poll.join().unwrap());
+                // Dropped outside the measurement
+                set
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn insert_sharded(c: &mut Criterion) {
+    c.bench_function("insert/sharded_insert_many", |b| {
+        b.iter_batched(
+            || Arc::new(ids("seen", SEEN).collect::<SeenSet>()),
+            |set| {
+                let polls: Vec<_> = (0..POLLS)
+                    .map(|poll| {
+                        let set = set.clone();
+                        thread::spawn(move || {
+                            let prefix = format!("poll{}", poll);
+                            let mut all = ids(&prefix, RESULT_SET * ROUNDS);
+                            for _ in 0..ROUNDS {
+                                black_box(set.insert_many(all.by_ref().take(RESULT_SET)));
+                            }
+                        })
+                    })
+                    .collect();
+                polls.into_iter().for_each(|poll| poll.join().unwrap());
+                // Dropped outside the measurement
+                set
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+/// What the persistence service does every interval: walk every ID.
+fn snapshot(c: &mut Criterion) {
+    let global = Mutex::new(ids("seen", SEEN).collect::<HashSet<_>>());
+    c.bench_function("snapshot/global_clone", |b| {
+        b.iter(|| {
+            let copy = global.blocking_lock().clone();
+            black_box(copy.iter().map(String::len).sum::<usize>())
+        })
+    });
+
+    let sharded: SeenSet = ids("seen", SEEN).collect();
+    c.bench_function("snapshot/sharded_for_each", |b| {
+        b.iter(|| {
+            let mut bytes = 0;
+            sharded.for_each(|id| bytes += id.len());
+            black_box(bytes)
+        })
+    });
+}
+
+criterion_group!(benches, insert_global, insert_sharded, snapshot);
+criterion_main!(benches);
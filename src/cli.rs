@@ -0,0 +1,67 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Args, Parser, Subcommand};
+use crate::config::ExportFormat;
+
+/// Watches Amazon's job search and sends new postings to Telegram. Runs the
+/// monitor unless a command is given.
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Write the job history to CSV, JSON Lines or Parquet files. Options
+    /// default to the `[export]` section of config.toml.
+    Export(ExportArgs),
+    /// Print posting and pay statistics from the job history.
+    Report(ReportArgs),
+}
+
+#[derive(Args)]
+pub struct ReportArgs {
+    /// How many days back to cover. Defaults to `report.days`.
+    #[arg(long)]
+    pub days: Option<u32>,
+    /// Print JSON instead of tables.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    #[arg(long, value_enum)]
+    pub format: Option<ExportFormat>,
+    /// Directory to write to.
+    #[arg(long)]
+    pub out: Option<String>,
+    /// Comma-separated columns, e.g. `id,title,pay_max`.
+    #[arg(long, value_delimiter = ',')]
+    pub columns: Vec<String>,
+    /// Write a single file instead of one per day.
+    #[arg(long)]
+    pub no_partition: bool,
+    /// Case-insensitive substring of the location.
+    #[arg(long)]
+    pub location: Option<String>,
+    /// Job type such as `full` or `PART_TIME`.
+    #[arg(long = "type")]
+    pub job_type: Option<String>,
+    /// Lower bound on the best advertised pay.
+    #[arg(long)]
+    pub min_pay: Option<f64>,
+    /// Only jobs first seen since this date (`2026-10-01`) or time (RFC 3339).
+    #[arg(long, value_parser = parse_since)]
+    pub since: Option<DateTime<Utc>>,
+}
+
+fn parse_since(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|day| day.and_time(Default::default()).and_utc())
+        .map_err(|_| format!("`{}` is neither a date like 2026-10-01 nor an RFC 3339 time", value))
+}
@@ -1,7 +1,55 @@
 use anyhow::{Context, Result};
+use chrono::NaiveTime;
+use crate::model::{ExportColumn, JobFilter, JobInfo};
+use crate::utils::{load_ca_bundle, parse_time_range};
+use chrono_tz::Tz;
+use croner::Cron;
+use reqwest::header::{HeaderName, HeaderValue};
 use serde::Deserialize;
 use serde::Serialize;
-use std::{fs, path::Path};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::{env, fmt, fs, path::Path};
+
+const ENV_PREFIX: &str = "JOBLOG__";
+const PLACEHOLDERS: &[&str] = &["YOUR_API_TOKEN", "YOUR_BOT_TOKEN", "YOUR_CHAT_ID"];
+const REDACTED: &str = "[REDACTED]";
+/// Runtime secrets kept for redaction. A token can still show up in errors
+/// for a while after the next one replaced it.
+const MAX_RUNTIME_SECRETS: usize = 4;
+
+/// A credential that never shows up in `Debug` output. Use [`Secret::expose`]
+/// at the single point where the raw value is needed.
+#[derive(Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Secret(value.to_string())
+    }
+}
+
+/// Which parts of the config a command relies on, so commands that only
+/// read local files run without the monitor's credentials.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Monitor,
+    /// Persistence paths and the `export` and `report` sections.
+    LocalData,
+}
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Config {
@@ -9,50 +57,1019 @@ pub struct Config {
     pub telegram: TelegramConfig,
     pub persistence: PersistenceConfig,
     pub rate_limiting: RateLimitingConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub scheduler: SchedulerConfig,
+    #[serde(default)]
+    pub schema: SchemaConfig,
+    #[serde(default)]
+    pub server: ServerConfig,
+    #[serde(default)]
+    pub supervisor: SupervisorConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub export: ExportConfig,
+    #[serde(default)]
+    pub report: ReportConfig,
+    /// Warnings raised while loading, logged once the logger is up.
+    #[serde(skip)]
+    pub notices: Vec<String>,
+    /// Secrets learned at runtime, like fetched API tokens. Shared by every
+    /// clone so all services redact them.
+    #[serde(skip)]
+    runtime_secrets: Arc<RwLock<VecDeque<Secret>>>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct AmazonConfig {
     pub api_url: String,
-    pub api_token: String,
+    #[serde(default)]
+    pub api_token: Secret,
+    /// Read `api_token` from this file instead (Docker/K8s secrets).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_token_file: Option<String>,
     pub country: String,
     pub locale: String,
     pub page_size: usize,
+    /// When set, `api_token` is ignored and tokens come from this refresh flow.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthConfig>,
+    /// Named searches polled in turn. Empty means a single `default`
+    /// profile using `country` and `locale` above.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<ProfileConfig>,
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxySelection {
+    /// Each request takes the next proxy in order.
+    #[default]
+    RoundRobin,
+    /// Each request takes the proxy that has been idle longest.
+    LeastRecentlyUsed,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ProxyConfig {
+    /// `http://`, `https://` or `socks5://` URLs, optionally with
+    /// `user:password@`. One proxies every request, several form a pool.
+    /// Empty connects directly.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub urls: Vec<Secret>,
+    pub selection: ProxySelection,
+    /// Network errors, timeouts or throttling in a row before a proxy is
+    /// taken out of the pool.
+    pub eject_after_failures: u32,
+    /// How long an ejected proxy sits out.
+    pub eject_secs: u64,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        ProxyConfig {
+            urls: Vec::new(),
+            selection: ProxySelection::default(),
+            eject_after_failures: 3,
+            eject_secs: 300,
+        }
+    }
+}
+
+/// Client settings shared by every outgoing connection: Amazon, the token
+/// endpoint and Telegram.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// Time allowed to establish a connection, TLS included.
+    pub connect_timeout_ms: u64,
+    /// Longest wait for the next chunk of a response.
+    pub read_timeout_ms: u64,
+    /// Cap on a whole request, from connecting to the last byte.
+    pub request_timeout_ms: u64,
+    /// Idle connections are closed after this long.
+    pub pool_idle_timeout_secs: u64,
+    pub pool_max_idle_per_host: usize,
+    /// Negotiate HTTP/2 where the server offers it. Off forces HTTP/1.1.
+    pub http2: bool,
+    /// PEM file of extra CA certificates to trust, e.g. for an intercepting
+    /// corporate proxy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<String>,
+    /// Amazon requests each pick one of these at random. Empty sends the
+    /// client's own defaults.
+    pub header_profiles: Vec<HeaderProfile>,
+}
+
+/// Headers Amazon requests always carry, which a header profile would only
+/// duplicate.
+const RESERVED_HEADERS: [&str; 4] = ["authorization", "content-type", "country", "user-agent"];
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct HeaderProfile {
+    pub user_agent: String,
+    /// Extra headers sent along, e.g. `Accept-Language`. Not the ones every
+    /// request sets anyway, like `Authorization`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+impl HeaderProfile {
+    fn browser(user_agent: &str) -> Self {
+        HeaderProfile { user_agent: user_agent.into(), headers: BTreeMap::new() }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            connect_timeout_ms: 10_000,
+            read_timeout_ms: 30_000,
+            request_timeout_ms: 30_000,
+            pool_idle_timeout_secs: 90,
+            pool_max_idle_per_host: 16,
+            http2: true,
+            ca_bundle: None,
+            header_profiles: vec![
+                HeaderProfile::browser("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36"),
+                HeaderProfile::browser("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/14.1.1 Safari/605.1.15"),
+                HeaderProfile::browser("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/92.0.4515.107 Safari/537.36"),
+                HeaderProfile::browser("Mozilla/5.0 (iPhone; CPU iPhone OS 14_6 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/14.1.1 Mobile/15E148 Safari/604.1"),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ProfileConfig {
+    pub name: String,
+    /// Defaults to `amazon.country`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    /// Defaults to `amazon.locale`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    #[serde(default)]
+    pub keywords: String,
+}
+
+/// A profile with its defaults filled in from `[amazon]`.
+#[derive(Debug, Clone)]
+pub struct SearchProfile {
+    pub name: String,
+    pub country: String,
+    pub locale: String,
+    pub keywords: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AuthConfig {
+    pub token_url: String,
+    /// Sent as the `refresh_token` form field when set.
+    #[serde(default)]
+    pub refresh_token: Secret,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token_file: Option<String>,
+    /// Extra form fields for the token request, e.g. `grant_type`.
+    #[serde(default)]
+    pub form: BTreeMap<String, String>,
+    #[serde(default = "default_token_field")]
+    pub token_field: String,
+    #[serde(default = "default_expires_in_field")]
+    pub expires_in_field: String,
+    /// Lifetime assumed when the response carries no expiry.
+    #[serde(default = "default_token_ttl_secs")]
+    pub default_ttl_secs: u64,
+    /// Refresh this long before the cached token expires, or halfway
+    /// through a token that lives less than twice this.
+    #[serde(default = "default_refresh_margin_secs")]
+    pub refresh_margin_secs: u64,
+}
+
+fn default_token_field() -> String {
+    "access_token".into()
+}
+
+fn default_expires_in_field() -> String {
+    "expires_in".into()
+}
+
+fn default_token_ttl_secs() -> u64 {
+    3600
+}
+
+fn default_refresh_margin_secs() -> u64 {
+    60
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TelegramConfig {
-    pub bot_token: String,
+    /// Bot API base URL. Only worth changing for a self-hosted Bot API
+    /// server or a local stand-in.
+    #[serde(default = "default_telegram_api_url")]
+    pub api_url: String,
+    #[serde(default)]
+    pub bot_token: Secret,
+    /// Read `bot_token` from this file instead (Docker/K8s secrets).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bot_token_file: Option<String>,
+    /// Operator alerts and reports go here, and job notifications too unless
+    /// `subscribers` are configured.
+    #[serde(deserialize_with = "text_or_number")]
+    pub chat_id: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subscribers: Vec<SubscriberConfig>,
+    /// How long to collect jobs for the same location and subscriber into one
+    /// message, up to a minute. 0 sends every batch as it comes. Priority
+    /// jobs never wait.
+    #[serde(default = "default_coalesce_window_ms")]
+    pub coalesce_window_ms: u64,
+}
+
+/// Chat IDs are numbers, so one set through an env override for a key the
+/// file leaves out arrives as one.
+fn text_or_number<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Text(String),
+        Number(i64),
+    }
+    Ok(match Raw::deserialize(deserializer)? {
+        Raw::Text(text) => text,
+        Raw::Number(number) => number.to_string(),
+    })
+}
+
+fn default_telegram_api_url() -> String {
+    "https://api.telegram.org".into()
+}
+
+fn default_coalesce_window_ms() -> u64 {
+    5000
+}
+
+/// Longer windows delay jobs more than they save messages.
+const MAX_COALESCE_WINDOW_MS: u64 = 60_000;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryMode {
+    /// A message as soon as matching jobs are found.
+    #[default]
+    Instant,
+    /// Matching jobs are collected and sent on `digest_schedule`.
+    Digest,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SubscriberConfig {
+    pub name: String,
+    pub chat_id: String,
+    #[serde(default)]
+    pub mode: DeliveryMode,
+    /// Cron pattern (`minute hour day month weekday`) for digests.
+    #[serde(default = "default_digest_schedule")]
+    pub digest_schedule: String,
+    /// IANA name like `"Europe/Berlin"` the schedule is read in. Defaults to
+    /// the machine's local time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// Only jobs matching this are sent to the subscriber.
+    #[serde(default)]
+    pub filter: JobFilter,
+    /// `"HH:MM-HH:MM"` in `timezone` during which jobs don't ping, e.g.
+    /// `"22:00-07:00"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<String>,
+    #[serde(default)]
+    pub quiet_mode: QuietMode,
+    /// Jobs matching these are sent at once with a sound, even during quiet
+    /// hours or to a digest subscriber.
+    #[serde(default)]
+    pub priority: PriorityRule,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QuietMode {
+    /// Send as usual but without a notification sound.
+    #[default]
+    Silent,
+    /// Hold jobs, and digests falling due, and send them as one digest when
+    /// quiet hours end.
+    Hold,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct PriorityRule {
+    /// Jobs advertising at least this much (maximum pay, else minimum).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_pay: Option<f64>,
+    /// Jobs whose location contains any of these, ignoring case.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub locations: Vec<String>,
+}
+
+impl PriorityRule {
+    pub fn matches(&self, job: &JobInfo) -> bool {
+        let pay = job.pay_max.or(job.pay_min);
+        let location = job.location.to_lowercase();
+        self.min_pay.is_some_and(|min_pay| pay.is_some_and(|pay| pay >= min_pay))
+            || self.locations.iter().any(|watched| location.contains(&watched.to_lowercase()))
+    }
+}
+
+fn default_digest_schedule() -> String {
+    "0 8 * * *".into()
+}
+
+/// A subscriber with its schedule and timezone parsed.
+#[derive(Debug, Clone)]
+pub struct Subscriber {
+    pub name: String,
     pub chat_id: String,
+    pub mode: DeliveryMode,
+    pub digest_schedule: Cron,
+    pub timezone: Option<Tz>,
+    pub filter: JobFilter,
+    pub quiet_hours: Option<(NaiveTime, NaiveTime)>,
+    pub quiet_mode: QuietMode,
+    pub priority: PriorityRule,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PersistenceConfig {
+    /// Snapshot of the seen set, rewritten on every compaction.
     pub seen_jobs_file: String,
+    /// How often the journal is folded into `seen_jobs_file` and the job
+    /// history is saved.
     pub persist_interval_secs: u64,
+    /// Seen-set changes since the last snapshot, appended as they happen.
+    #[serde(default = "default_journal_file")]
+    pub journal_file: String,
+    /// Changes are written and fsynced together at most this often.
+    #[serde(default = "default_journal_flush_ms")]
+    pub journal_flush_ms: u64,
+    /// Details of every listed job, one JSON record per line.
+    #[serde(default = "default_history_file")]
+    pub history_file: String,
+    /// A job no profile has listed for this long is marked closed. Jobs
+    /// also drop off a page when newer ones push them past `page_size`, so
+    /// this shouldn't be shorter than a busy hour.
+    #[serde(default = "default_close_after_secs")]
+    pub close_after_secs: u64,
+}
+
+fn default_journal_file() -> String {
+    "seen_jobs.journal".into()
+}
+
+fn default_journal_flush_ms() -> u64 {
+    500
+}
+
+fn default_history_file() -> String {
+    "job_history.jsonl".into()
+}
+
+fn default_close_after_secs() -> u64 {
+    6 * 3600
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct RateLimitingConfig {
     pub requests_per_second: usize,
-    pub delay_between_requests_ms: u64,
     pub retry_base_ms: u64,
     pub retry_max_delay_ms: u64,
     pub max_retries: usize,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    /// Upper bound the poll interval widens to while results stay unchanged.
+    pub max_interval_ms: u64,
+    /// Multiplier applied to the interval after each unchanged poll.
+    pub widen_factor: f64,
+    /// Local-time ranges like `"06:00-10:00"` during which polling stays at
+    /// the full `requests_per_second` rate.
+    pub hot_hours: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct SchemaConfig {
+    /// Where raw responses are saved the first time a new shape shows up.
+    pub capture_dir: String,
+}
+
+impl Default for SchemaConfig {
+    fn default() -> Self {
+        SchemaConfig {
+            capture_dir: "schema_captures".into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub enabled: bool,
+    /// Address the HTTP server (metrics and friends) listens on.
+    pub bind: String,
+    /// `/readyz` fails once the last successful fetch is older than this.
+    pub ready_max_fetch_age_secs: u64,
+    /// How many of the newest jobs the dashboard lists.
+    pub dashboard_recent_jobs: usize,
+    /// Bearer token for the JSON API. The API is off while this is empty.
+    pub api_token: Secret,
+    /// Read `api_token` from this file instead (Docker/K8s secrets).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_token_file: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ReportConfig {
+    /// Send the report to Telegram once a week.
+    pub weekly_digest: bool,
+    /// Weekday the digest goes out, e.g. `"mon"`.
+    pub digest_day: String,
+    /// Local time the digest goes out, `"HH:MM"`.
+    pub digest_time: String,
+    /// How many days back the report covers.
+    pub days: u32,
+    /// Rows per section in the Telegram digest.
+    pub max_rows: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+    /// Needs the `parquet` feature.
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// Defaults for the `export` command and the continuous sink.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ExportConfig {
+    /// Append every new job to `new_jobs.<ext>` in `dir` as soon as it is
+    /// found.
+    pub sink_enabled: bool,
+    pub format: ExportFormat,
+    pub dir: String,
+    /// Write one file per day a job was first seen, under `date=YYYY-MM-DD/`.
+    pub partition_by_day: bool,
+    /// Columns to write, all of them when empty.
+    pub columns: Vec<String>,
+    /// Only jobs matching this are written.
+    pub filter: JobFilter,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Hourly,
+    Daily,
+    Never,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Filter directives such as `"info,job_log_moduler::services=debug"`.
+    /// `RUST_LOG` takes precedence when set.
+    pub level: String,
+    pub format: LogFormat,
+    /// Also write logs to rotating files in this directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_dir: Option<String>,
+    pub file_rotation: LogRotation,
+    pub file_prefix: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            level: "info".into(),
+            format: LogFormat::Text,
+            file_dir: None,
+            file_rotation: LogRotation::Daily,
+            file_prefix: "job-monitor.log".into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct SupervisorConfig {
+    pub restart_base_ms: u64,
+    pub restart_max_delay_ms: u64,
+    /// A task that restarts this many times within `flap_window_secs` is
+    /// reported to the operator.
+    pub flap_threshold: usize,
+    pub flap_window_secs: u64,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        SupervisorConfig {
+            restart_base_ms: 1_000,
+            restart_max_delay_ms: 60_000,
+            flap_threshold: 3,
+            flap_window_secs: 600,
+        }
+    }
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        ReportConfig {
+            weekly_digest: false,
+            digest_day: "mon".into(),
+            digest_time: "09:00".into(),
+            days: 7,
+            max_rows: 10,
+        }
+    }
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        ExportConfig {
+            sink_enabled: false,
+            format: ExportFormat::Csv,
+            dir: "exports".into(),
+            partition_by_day: true,
+            columns: Vec::new(),
+            filter: JobFilter::default(),
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            enabled: true,
+            bind: "127.0.0.1:9090".into(),
+            ready_max_fetch_age_secs: 120,
+            dashboard_recent_jobs: 50,
+            api_token: Secret::default(),
+            api_token_file: None,
+        }
+    }
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        SchedulerConfig {
+            max_interval_ms: 10_000,
+            widen_factor: 1.5,
+            hot_hours: Vec::new(),
+        }
+    }
+}
+
 impl Config {
-    pub fn load() -> Result<Self> {
+    pub fn load(scope: Scope) -> Result<Self> {
+        // Pick up secrets from a local .env file, if any
+        dotenvy::dotenv().ok();
+
         let config_path = "config.toml";
-        if !Path::new(config_path).exists() {
+        let created_default = !Path::new(config_path).exists();
+        if created_default {
             Self::create_default_config(config_path)?;
         }
 
         let config_content = fs::read_to_string(config_path)
             .with_context(|| format!("Failed to read config file: {}", config_path))?;
-        
-        toml::from_str(&config_content)
-            .with_context(|| "Failed to parse config file")
+
+        let mut value: toml::Value = toml::from_str(&config_content)
+            .with_context(|| "Failed to parse config file")?;
+        let mut notices = Vec::new();
+        apply_env_overrides(&mut value, env::vars(), &mut notices);
+
+        let mut config: Config = value
+            .try_into()
+            .with_context(|| "Failed to parse config file")?;
+        if scope == Scope::Monitor {
+            config.read_secret_files()?;
+        }
+        if let Err(e) = config.validate(scope) {
+            return Err(if created_default {
+                e.context(format!("Created a default {}, fill in your credentials", config_path))
+            } else {
+                e
+            });
+        }
+
+        if created_default {
+            notices.push("Created default config file. Please update with your credentials.".into());
+        }
+        config.notices = notices;
+        Ok(config)
+    }
+
+    /// Checks every field and reports all problems at once, each prefixed
+    /// with its field path.
+    pub fn validate(&self, scope: Scope) -> Result<()> {
+        let mut problems = Vec::new();
+
+        if scope == Scope::Monitor {
+            self.check_monitor(&mut problems);
+        }
+        if self.persistence.history_file.trim().is_empty() {
+            problems.push("persistence.history_file: must not be empty".into());
+        }
+
+        let export = &self.export;
+        if let Err(e) = ExportColumn::parse_list(&export.columns) {
+            problems.push(format!("export.columns: {}", e));
+        }
+        if export.dir.trim().is_empty() {
+            problems.push("export.dir: must not be empty".into());
+        }
+        if export.sink_enabled && export.format == ExportFormat::Parquet {
+            problems.push("export.format: the continuous sink can only write csv or jsonl".into());
+        }
+
+        let report = &self.report;
+        if report.digest_day.parse::<chrono::Weekday>().is_err() {
+            problems.push(format!("report.digest_day: `{}` is not a weekday like \"mon\"", report.digest_day));
+        }
+        if chrono::NaiveTime::parse_from_str(&report.digest_time, "%H:%M").is_err() {
+            problems.push(format!("report.digest_time: `{}` is not a time like \"09:00\"", report.digest_time));
+        }
+        if report.days == 0 {
+            problems.push("report.days: must be at least 1".into());
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+
+        Err(anyhow::anyhow!(
+            "Invalid configuration ({} problem{}):\n  - {}",
+            problems.len(),
+            if problems.len() == 1 { "" } else { "s" },
+            problems.join("\n  - ")
+        ))
+    }
+
+    /// The sections only the monitor uses: credentials, endpoints and
+    /// everything that shapes polling and delivery.
+    fn check_monitor(&self, problems: &mut Vec<String>) {
+        if !self.amazon.api_url.starts_with("http://") && !self.amazon.api_url.starts_with("https://") {
+            problems.push(format!(
+                "amazon.api_url: `{}` is not an http(s) URL",
+                self.amazon.api_url
+            ));
+        }
+        match &self.amazon.auth {
+            None => check_secret(problems, "amazon.api_token", self.amazon.api_token.expose()),
+            Some(auth) => {
+                if !auth.token_url.starts_with("http://") && !auth.token_url.starts_with("https://") {
+                    problems.push(format!(
+                        "amazon.auth.token_url: `{}` is not an http(s) URL",
+                        auth.token_url
+                    ));
+                }
+                if auth.token_field.trim().is_empty() {
+                    problems.push("amazon.auth.token_field: must not be empty".into());
+                }
+                if auth.default_ttl_secs <= auth.refresh_margin_secs {
+                    problems.push(format!(
+                        "amazon.auth.default_ttl_secs: {} must be larger than amazon.auth.refresh_margin_secs ({})",
+                        auth.default_ttl_secs, auth.refresh_margin_secs
+                    ));
+                }
+            }
+        }
+        if self.amazon.country.trim().is_empty() {
+            problems.push("amazon.country: must not be empty".into());
+        }
+        if self.amazon.locale.trim().is_empty() {
+            problems.push("amazon.locale: must not be empty".into());
+        }
+        if self.amazon.page_size == 0 {
+            problems.push("amazon.page_size: must be at least 1".into());
+        }
+        for (i, profile) in self.amazon.profiles.iter().enumerate() {
+            if profile.name.trim().is_empty() {
+                problems.push(format!("amazon.profiles[{}].name: must not be empty", i));
+            } else if self.amazon.profiles[..i].iter().any(|p| p.name == profile.name) {
+                problems.push(format!(
+                    "amazon.profiles[{}].name: `{}` is used by more than one profile",
+                    i, profile.name
+                ));
+            }
+        }
+
+        if !self.telegram.api_url.starts_with("http://") && !self.telegram.api_url.starts_with("https://") {
+            problems.push(format!(
+                "telegram.api_url: `{}` is not an http(s) URL",
+                self.telegram.api_url
+            ));
+        }
+        check_secret(problems, "telegram.bot_token", self.telegram.bot_token.expose());
+        check_secret(problems, "telegram.chat_id", &self.telegram.chat_id);
+        if self.telegram.coalesce_window_ms > MAX_COALESCE_WINDOW_MS {
+            problems.push(format!("telegram.coalesce_window_ms: must be at most {}", MAX_COALESCE_WINDOW_MS));
+        }
+        for (i, subscriber) in self.telegram.subscribers.iter().enumerate() {
+            let field = format!("telegram.subscribers[{}]", i);
+            if subscriber.name.trim().is_empty() {
+                problems.push(format!("{}.name: must not be empty", field));
+            } else if self.telegram.subscribers[..i].iter().any(|s| s.name == subscriber.name) {
+                problems.push(format!(
+                    "{}.name: `{}` is used by more than one subscriber",
+                    field, subscriber.name
+                ));
+            }
+            if subscriber.chat_id.trim().is_empty() {
+                problems.push(format!("{}.chat_id: must not be empty", field));
+            }
+            if let Err(e) = subscriber.digest_schedule.parse::<Cron>() {
+                problems.push(format!(
+                    "{}.digest_schedule: `{}` is not a cron pattern like \"0 8 * * *\": {}",
+                    field, subscriber.digest_schedule, e
+                ));
+            }
+            if let Some(timezone) = &subscriber.timezone
+                && timezone.parse::<Tz>().is_err()
+            {
+                problems.push(format!(
+                    "{}.timezone: `{}` is not a timezone like \"Europe/Berlin\"",
+                    field, timezone
+                ));
+            }
+            if let Some(range) = &subscriber.quiet_hours
+                && parse_time_range(range).is_none()
+            {
+                problems.push(format!(
+                    "{}.quiet_hours: `{}` is not a range like \"22:00-07:00\"",
+                    field, range
+                ));
+            }
+        }
+
+        let proxy = &self.amazon.proxy;
+        for (i, url) in proxy.urls.iter().enumerate() {
+            let scheme = url.expose().split_once("://").map(|(scheme, _)| scheme);
+            if !matches!(scheme, Some("http" | "https" | "socks5" | "socks5h"))
+                || reqwest::Proxy::all(url.expose()).is_err()
+            {
+                // The URL may carry a password, so don't echo it
+                problems.push(format!(
+                    "amazon.proxy.urls[{}]: not a proxy URL like \"http://host:3128\" or \"socks5://host:1080\"",
+                    i
+                ));
+            }
+        }
+        if proxy.eject_after_failures == 0 {
+            problems.push("amazon.proxy.eject_after_failures: must be at least 1".into());
+        }
+
+        if self.persistence.seen_jobs_file.trim().is_empty() {
+            problems.push("persistence.seen_jobs_file: must not be empty".into());
+        }
+        if self.persistence.journal_file.trim().is_empty() {
+            problems.push("persistence.journal_file: must not be empty".into());
+        }
+        if self.schema.capture_dir.trim().is_empty() {
+            problems.push("schema.capture_dir: must not be empty".into());
+        }
+        if self.server.enabled && self.server.bind.parse::<std::net::SocketAddr>().is_err() {
+            problems.push(format!(
+                "server.bind: `{}` is not an address like \"127.0.0.1:9090\"",
+                self.server.bind
+            ));
+        }
+        let supervisor = &self.supervisor;
+        if supervisor.restart_base_ms > supervisor.restart_max_delay_ms {
+            problems.push(format!(
+                "supervisor.restart_base_ms: {} is larger than supervisor.restart_max_delay_ms ({})",
+                supervisor.restart_base_ms, supervisor.restart_max_delay_ms
+            ));
+        }
+        if supervisor.flap_threshold == 0 {
+            problems.push("supervisor.flap_threshold: must be at least 1".into());
+        }
+        if self.persistence.persist_interval_secs == 0 {
+            problems.push("persistence.persist_interval_secs: must be at least 1".into());
+        }
+        if self.persistence.journal_flush_ms == 0 {
+            problems.push("persistence.journal_flush_ms: must be at least 1".into());
+        }
+
+        let rate = &self.rate_limiting;
+        if rate.requests_per_second == 0 {
+            problems.push("rate_limiting.requests_per_second: must be at least 1".into());
+        }
+        if rate.max_retries == 0 {
+            problems.push("rate_limiting.max_retries: must be at least 1".into());
+        }
+        if rate.retry_base_ms > rate.retry_max_delay_ms {
+            problems.push(format!(
+                "rate_limiting.retry_base_ms: {} is larger than rate_limiting.retry_max_delay_ms ({})",
+                rate.retry_base_ms, rate.retry_max_delay_ms
+            ));
+        }
+
+        let http = &self.http;
+        for (field, value) in [
+            ("connect_timeout_ms", http.connect_timeout_ms),
+            ("read_timeout_ms", http.read_timeout_ms),
+            ("request_timeout_ms", http.request_timeout_ms),
+        ] {
+            if value == 0 {
+                problems.push(format!("http.{}: must be at least 1", field));
+            }
+        }
+        if let Some(path) = &http.ca_bundle
+            && let Err(e) = load_ca_bundle(path)
+        {
+            problems.push(format!("http.ca_bundle: {:#}", e));
+        }
+        for (i, profile) in http.header_profiles.iter().enumerate() {
+            if HeaderValue::from_str(&profile.user_agent).is_err() || profile.user_agent.trim().is_empty() {
+                problems.push(format!("http.header_profiles[{}].user_agent: must be a non-empty header value", i));
+            }
+            for (name, value) in &profile.headers {
+                match HeaderName::from_bytes(name.as_bytes()) {
+                    Ok(header) if RESERVED_HEADERS.contains(&header.as_str()) => problems.push(format!(
+                        "http.header_profiles[{}].headers: `{}` is set on every request already",
+                        i, name
+                    )),
+                    Ok(_) if HeaderValue::from_str(value).is_ok() => {}
+                    _ => problems.push(format!("http.header_profiles[{}].headers: `{}` is not a valid header", i, name)),
+                }
+            }
+        }
+
+        let scheduler = &self.scheduler;
+        if scheduler.widen_factor.is_nan() || scheduler.widen_factor < 1.0 {
+            problems.push(format!(
+                "scheduler.widen_factor: {} must be at least 1.0",
+                scheduler.widen_factor
+            ));
+        }
+        if rate.requests_per_second > 0
+            && scheduler.max_interval_ms < 1000 / rate.requests_per_second as u64
+        {
+            problems.push(format!(
+                "scheduler.max_interval_ms: {} is shorter than the base interval of {}ms implied by rate_limiting.requests_per_second",
+                scheduler.max_interval_ms,
+                1000 / rate.requests_per_second as u64
+            ));
+        }
+        for (i, range) in scheduler.hot_hours.iter().enumerate() {
+            if parse_time_range(range).is_none() {
+                problems.push(format!(
+                    "scheduler.hot_hours[{}]: `{}` is not a range like \"06:00-10:00\"",
+                    i, range
+                ));
+            }
+        }
+    }
+
+    pub fn profiles(&self) -> Vec<SearchProfile> {
+        if self.amazon.profiles.is_empty() {
+            return vec![SearchProfile {
+                name: "default".into(),
+                country: self.amazon.country.clone(),
+                locale: self.amazon.locale.clone(),
+                keywords: String::new(),
+            }];
+        }
+
+        self.amazon
+            .profiles
+            .iter()
+            .map(|profile| SearchProfile {
+                name: profile.name.clone(),
+                country: profile.country.clone().unwrap_or_else(|| self.amazon.country.clone()),
+                locale: profile.locale.clone().unwrap_or_else(|| self.amazon.locale.clone()),
+                keywords: profile.keywords.clone(),
+            })
+            .collect()
+    }
+
+    /// Who job notifications go to. Without configured subscribers that is a
+    /// single instant `default` subscriber on `telegram.chat_id`.
+    pub fn subscribers(&self) -> Vec<Subscriber> {
+        if self.telegram.subscribers.is_empty() {
+            return vec![Subscriber {
+                name: "default".into(),
+                chat_id: self.telegram.chat_id.clone(),
+                mode: DeliveryMode::Instant,
+                digest_schedule: default_digest_schedule().parse().expect("default schedule is valid"),
+                timezone: None,
+                filter: JobFilter::default(),
+                quiet_hours: None,
+                quiet_mode: QuietMode::default(),
+                priority: PriorityRule::default(),
+            }];
+        }
+
+        self.telegram
+            .subscribers
+            .iter()
+            .map(|subscriber| Subscriber {
+                name: subscriber.name.clone(),
+                chat_id: subscriber.chat_id.clone(),
+                mode: subscriber.mode,
+                digest_schedule: subscriber.digest_schedule.parse().expect("validated on load"),
+                timezone: subscriber.timezone.as_ref().map(|tz| tz.parse().expect("validated on load")),
+                filter: subscriber.filter.clone(),
+                quiet_hours: subscriber
+                    .quiet_hours
+                    .as_ref()
+                    .map(|range| parse_time_range(range).expect("validated on load")),
+                quiet_mode: subscriber.quiet_mode,
+                priority: subscriber.priority.clone(),
+            })
+            .collect()
+    }
+
+    /// Replaces every configured secret in `text` with a marker. Apply this to
+    /// anything built from a request or response before it reaches a log or
+    /// an error, since reqwest errors embed the full URL.
+    pub fn redact(&self, text: &str) -> String {
+        let refresh_token = self.amazon.auth.as_ref().map(|auth| &auth.refresh_token);
+        let runtime_secrets = self.runtime_secrets.read().expect("runtime secrets lock poisoned");
+        [
+            Some(&self.amazon.api_token),
+            Some(&self.telegram.bot_token),
+            Some(&self.server.api_token),
+            refresh_token,
+        ]
+            .into_iter()
+            .flatten()
+            .chain(&self.amazon.proxy.urls)
+            .chain(runtime_secrets.iter())
+            .map(Secret::expose)
+            .filter(|secret| !secret.is_empty())
+            .fold(text.to_string(), |text, secret| text.replace(secret, REDACTED))
+    }
+
+    /// Makes [`Config::redact`] scrub `secret` too, in this config and every
+    /// clone of it.
+    pub fn add_runtime_secret(&self, secret: Secret) {
+        let mut runtime_secrets = self.runtime_secrets.write().expect("runtime secrets lock poisoned");
+        if runtime_secrets.len() == MAX_RUNTIME_SECRETS {
+            runtime_secrets.pop_front();
+        }
+        runtime_secrets.push_back(secret);
+    }
+
+    fn read_secret_files(&mut self) -> Result<()> {
+        if let Some(path) = &self.amazon.api_token_file {
+            self.amazon.api_token = read_secret_file(path)
+                .with_context(|| "Failed to load amazon.api_token_file")?;
+        }
+        if let Some(auth) = &mut self.amazon.auth
+            && let Some(path) = &auth.refresh_token_file
+        {
+            auth.refresh_token = read_secret_file(path)
+                .with_context(|| "Failed to load amazon.auth.refresh_token_file")?;
+        }
+        if let Some(path) = &self.telegram.bot_token_file {
+            self.telegram.bot_token = read_secret_file(path)
+                .with_context(|| "Failed to load telegram.bot_token_file")?;
+        }
+        if let Some(path) = &self.server.api_token_file {
+            self.server.api_token = read_secret_file(path)
+                .with_context(|| "Failed to load server.api_token_file")?;
+        }
+        Ok(())
     }
 
     fn create_default_config(path: &str) -> Result<()> {
@@ -60,31 +1077,334 @@ impl Config {
             amazon: AmazonConfig {
                 api_url: "https://e5mquma77feepi2.amazonaws.com/graphql".into(),
                 api_token: "YOUR_API_TOKEN".into(),
+                api_token_file: None,
                 country: "Canada".into(),
                 locale: "en-US".into(),
                 page_size: 100,
+                auth: None,
+                profiles: Vec::new(),
+                proxy: ProxyConfig::default(),
             },
             telegram: TelegramConfig {
+                api_url: default_telegram_api_url(),
                 bot_token: "YOUR_BOT_TOKEN".into(),
+                bot_token_file: None,
                 chat_id: "YOUR_CHAT_ID".into(),
+                subscribers: Vec::new(),
+                coalesce_window_ms: default_coalesce_window_ms(),
             },
             persistence: PersistenceConfig {
                 seen_jobs_file: "seen_jobs.txt".into(),
                 persist_interval_secs: 300,
+                journal_file: default_journal_file(),
+                journal_flush_ms: default_journal_flush_ms(),
+                history_file: default_history_file(),
+                close_after_secs: default_close_after_secs(),
             },
             rate_limiting: RateLimitingConfig {
                 requests_per_second: 2,
-                delay_between_requests_ms: 300,
                 retry_base_ms: 500,
                 retry_max_delay_ms: 10_000,
                 max_retries: 5,
             },
+            http: HttpConfig::default(),
+            scheduler: SchedulerConfig::default(),
+            schema: SchemaConfig::default(),
+            server: ServerConfig::default(),
+            supervisor: SupervisorConfig::default(),
+            logging: LoggingConfig::default(),
+            export: ExportConfig::default(),
+            report: ReportConfig::default(),
+            notices: Vec::new(),
+            runtime_secrets: Default::default(),
         };
 
         let toml = toml::to_string_pretty(&default_config)?;
         fs::write(path, toml)?;
-        
-        log::warn!("Created default config file. Please update with your credentials.");
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+fn read_secret_file(path: &str) -> Result<Secret> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read secret file: {}", path))?;
+    Ok(Secret(contents.trim().to_string()))
+}
+
+fn check_secret(problems: &mut Vec<String>, field: &str, value: &str) {
+    let env_name = format!("{}{}", ENV_PREFIX, field.replace('.', "__").to_uppercase());
+    let hint = if field == "telegram.chat_id" {
+        format!("set it in config.toml or via {}", env_name)
+    } else {
+        format!("set it in config.toml, via {} or with {}_file", env_name, field)
+    };
+    if value.trim().is_empty() {
+        problems.push(format!("{}: must not be empty; {}", field, hint));
+    } else if PLACEHOLDERS.contains(&value) {
+        problems.push(format!(
+            "{}: still set to the placeholder `{}`; {}",
+            field, value, hint
+        ));
+    }
+}
+
+/// Applies `JOBLOG__SECTION__KEY=value` environment variables on top of the
+/// parsed TOML. The existing value's type decides how `value` is parsed, so
+/// a numeric-looking `chat_id` stays a string and lists are split on commas;
+/// keys missing from the file are taken as strings.
+fn apply_env_overrides(
+    root: &mut toml::Value,
+    vars: impl Iterator<Item = (String, String)>,
+    notices: &mut Vec<String>,
+) {
+    for (name, raw) in vars {
+        let Some(path) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let keys: Vec<String> = path.split("__").map(|k| k.to_lowercase()).collect();
+        if keys.iter().any(|k| k.is_empty()) {
+            notices.push(format!("Ignoring malformed config override {}", name));
+            continue;
+        }
+
+        let (last, parents) = keys.split_last().expect("split yields at least one key");
+        let Some(table) = root.as_table_mut().and_then(|root| section_mut(root, parents)) else {
+            notices.push(format!("Ignoring config override {}: {} is not a section", name, path));
+            continue;
+        };
+
+        let value = match table.get(last) {
+            Some(toml::Value::Integer(_)) => raw.parse().map(toml::Value::Integer).ok(),
+            Some(toml::Value::Float(_)) => raw.parse().map(toml::Value::Float).ok(),
+            Some(toml::Value::Boolean(_)) => raw.parse().map(toml::Value::Boolean).ok(),
+            Some(toml::Value::Array(_)) => Some(toml::Value::Array(
+                raw.split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| toml::Value::String(item.to_string()))
+                    .collect(),
+            )),
+            Some(_) => None,
+            // Nothing to take the type from, so go by what the value spells
+            None => scalar(&raw),
+        }
+        .unwrap_or(toml::Value::String(raw));
+        table.insert(last.clone(), value);
+    }
+}
+
+/// `raw` as the TOML boolean, integer or float it spells, if any.
+fn scalar(raw: &str) -> Option<toml::Value> {
+    if let Ok(flag) = raw.parse() {
+        return Some(toml::Value::Boolean(flag));
+    }
+    if let Ok(number) = raw.parse() {
+        return Some(toml::Value::Integer(number));
+    }
+    raw.parse::<f64>().ok().filter(|number| number.is_finite()).map(toml::Value::Float)
+}
+
+fn section_mut<'a>(table: &'a mut toml::Table, keys: &[String]) -> Option<&'a mut toml::Table> {
+    match keys.split_first() {
+        None => Some(table),
+        Some((key, rest)) => {
+            let entry = table
+                .entry(key.clone())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            section_mut(entry.as_table_mut()?, rest)
+        }
+    }
+}
+
+#[cfg(test)]
+impl Config {
+    /// A minimal config that passes validation.
+    pub fn for_tests() -> Config {
+        toml::from_str(tests::VALID).expect("the test config parses")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(super) const VALID: &str = r#"
+[amazon]
+api_url = "https://example.com/graphql"
+api_token = "token"
+country = "Canada"
+locale = "en-US"
+page_size = 100
+
+[telegram]
+bot_token = "123:abc"
+chat_id = "42"
+
+[persistence]
+seen_jobs_file = "seen_jobs.txt"
+persist_interval_secs = 300
+
+[rate_limiting]
+requests_per_second = 2
+retry_base_ms = 500
+retry_max_delay_ms = 10000
+max_retries = 5
+"#;
+
+    fn overridden(vars: &[(&str, &str)]) -> (toml::Value, Vec<String>) {
+        let mut root: toml::Value = toml::from_str(VALID).unwrap();
+        let mut notices = Vec::new();
+        let vars = vars.iter().map(|(name, value)| (name.to_string(), value.to_string()));
+        apply_env_overrides(&mut root, vars, &mut notices);
+        (root, notices)
+    }
+
+    fn problems(config: &Config) -> String {
+        format!("{:#}", config.validate(Scope::Monitor).unwrap_err())
+    }
+
+    #[test]
+    fn overrides_take_the_type_of_the_existing_value() {
+        let (root, notices) = overridden(&[
+            ("JOBLOG__AMAZON__PAGE_SIZE", "50"),
+            ("JOBLOG__TELEGRAM__CHAT_ID", "1234"),
+            ("JOBLOG__RATE_LIMITING__MAX_RETRIES", "many"),
+        ]);
+        assert!(notices.is_empty());
+        assert_eq!(root["amazon"]["page_size"], toml::Value::Integer(50));
+        assert_eq!(root["telegram"]["chat_id"], toml::Value::String("1234".into()));
+        // Unparsable values are kept as strings for deserialization to reject
+        assert_eq!(root["rate_limiting"]["max_retries"], toml::Value::String("many".into()));
+    }
+
+    #[test]
+    fn overrides_of_missing_keys_go_by_what_they_spell() {
+        let (root, notices) = overridden(&[
+            ("JOBLOG__SERVER__ENABLED", "true"),
+            ("JOBLOG__SERVER__DASHBOARD_RECENT_JOBS", "20"),
+            ("JOBLOG__SCHEDULER__WIDEN_FACTOR", "2.5"),
+            ("JOBLOG__SERVER__BIND", "0.0.0.0:9090"),
+            ("JOBLOG__LOGGING__LEVEL", "inf"),
+        ]);
+        assert!(notices.is_empty());
+        assert_eq!(root["server"]["enabled"], toml::Value::Boolean(true));
+        assert_eq!(root["server"]["dashboard_recent_jobs"], toml::Value::Integer(20));
+        assert_eq!(root["scheduler"]["widen_factor"], toml::Value::Float(2.5));
+        assert_eq!(root["server"]["bind"], toml::Value::String("0.0.0.0:9090".into()));
+        assert_eq!(root["logging"]["level"], toml::Value::String("inf".into()));
+
+        let config: Config = root.try_into().unwrap();
+        assert!(config.server.enabled);
+        assert_eq!(config.server.dashboard_recent_jobs, 20);
+    }
+
+    #[test]
+    fn a_numeric_chat_id_override_is_read_as_text() {
+        let mut root: toml::Value = toml::from_str(VALID).unwrap();
+        root["telegram"].as_table_mut().unwrap().remove("chat_id");
+        let mut notices = Vec::new();
+        let vars = [("JOBLOG__TELEGRAM__CHAT_ID".to_string(), "-100123".to_string())];
+        apply_env_overrides(&mut root, vars.into_iter(), &mut notices);
+        let config: Config = root.try_into().unwrap();
+        assert_eq!(config.telegram.chat_id, "-100123");
+    }
+
+    #[test]
+    fn overrides_split_lists_and_create_sections() {
+        let mut root: toml::Value = toml::from_str(VALID).unwrap();
+        root["amazon"].as_table_mut().unwrap().insert("hosts".into(), toml::Value::Array(Vec::new()));
+        let mut notices = Vec::new();
+        let vars = [
+            ("JOBLOG__AMAZON__HOSTS", "a, b,,c"),
+            ("JOBLOG__SERVER__BIND", "0.0.0.0:9090"),
+            ("OTHER__AMAZON__PAGE_SIZE", "1"),
+        ];
+        apply_env_overrides(
+            &mut root,
+            vars.iter().map(|(name, value)| (name.to_string(), value.to_string())),
+            &mut notices,
+        );
+        assert!(notices.is_empty());
+        assert_eq!(
+            root["amazon"]["hosts"],
+            toml::Value::Array(vec!["a".into(), "b".into(), "c".into()])
+        );
+        assert_eq!(root["server"]["bind"], toml::Value::String("0.0.0.0:9090".into()));
+        assert_eq!(root["amazon"]["page_size"], toml::Value::Integer(100));
+    }
+
+    #[test]
+    fn malformed_overrides_are_reported_and_skipped() {
+        let (root, notices) = overridden(&[
+            ("JOBLOG__AMAZON____PAGE_SIZE", "1"),
+            ("JOBLOG__AMAZON__PAGE_SIZE__MAX", "1"),
+        ]);
+        assert_eq!(notices.len(), 2, "{:?}", notices);
+        assert!(notices[0].starts_with("Ignoring malformed config override"));
+        assert!(notices[1].contains("is not a section"));
+        assert_eq!(root["amazon"]["page_size"], toml::Value::Integer(100));
+    }
+
+    #[test]
+    fn a_valid_config_passes() {
+        let config: Config = toml::from_str(VALID).unwrap();
+        config.validate(Scope::Monitor).unwrap();
+    }
+
+    #[test]
+    fn validation_reports_every_problem() {
+        let mut config: Config = toml::from_str(VALID).unwrap();
+        config.amazon.api_url = "ftp://example.com".into();
+        config.amazon.page_size = 0;
+        config.telegram.bot_token = "YOUR_BOT_TOKEN".into();
+        config.telegram.coalesce_window_ms = 3_600_000;
+        config.rate_limiting.retry_base_ms = 20_000;
+        config.scheduler.hot_hours = vec!["morning".into()];
+
+        let problems = problems(&config);
+        assert!(problems.contains("(6 problems)"), "{}", problems);
+        assert!(problems.contains("amazon.api_url: `ftp://example.com` is not an http(s) URL"));
+        assert!(problems.contains("amazon.page_size: must be at least 1"));
+        assert!(problems.contains("telegram.bot_token: still set to the placeholder `YOUR_BOT_TOKEN`"));
+        assert!(problems.contains("telegram.coalesce_window_ms: must be at most 60000"));
+        assert!(problems.contains("rate_limiting.retry_base_ms: 20000 is larger than"));
+        assert!(problems.contains("scheduler.hot_hours[0]: `morning` is not a range"));
+    }
+
+    #[test]
+    fn header_profiles_cannot_repeat_request_headers() {
+        let mut config: Config = toml::from_str(VALID).unwrap();
+        let mut profile = HeaderProfile::browser("Mozilla/5.0");
+        profile.headers.insert("Accept-Language".into(), "en-CA".into());
+        profile.headers.insert("AUTHORIZATION".into(), "Bearer other".into());
+        profile.headers.insert("Country".into(), "United States".into());
+        config.http.header_profiles = vec![profile];
+
+        let problems = problems(&config);
+        assert!(problems.contains("(2 problems)"), "{}", problems);
+        assert!(problems.contains("http.header_profiles[0].headers: `AUTHORIZATION` is set on every request already"));
+        assert!(problems.contains("http.header_profiles[0].headers: `Country` is set on every request already"));
+    }
+
+    #[test]
+    fn local_commands_skip_the_monitor_sections() {
+        let mut config: Config = toml::from_str(VALID).unwrap();
+        config.amazon.api_token = Secret::default();
+        config.telegram.bot_token = "YOUR_BOT_TOKEN".into();
+        config.validate(Scope::LocalData).unwrap();
+
+        config.report.days = 0;
+        let problems = format!("{:#}", config.validate(Scope::LocalData).unwrap_err());
+        assert!(problems.contains("(1 problem)"), "{}", problems);
+        assert!(problems.contains("report.days: must be at least 1"));
+    }
+
+    #[test]
+    fn empty_secrets_point_at_the_env_variable() {
+        let mut config: Config = toml::from_str(VALID).unwrap();
+        config.amazon.api_token = Secret::default();
+        let problems = problems(&config);
+        assert!(problems.contains("amazon.api_token: must not be empty"), "{}", problems);
+        assert!(problems.contains("JOBLOG__AMAZON__API_TOKEN"));
+    }
+}
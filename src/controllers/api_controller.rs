@@ -0,0 +1,153 @@
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use crate::controllers::http_controller::HttpState;
+use crate::controllers::stream_controller;
+use crate::metrics::SEEN_JOBS;
+use crate::model::{JobFilter, Notification, NotificationState};
+use log::info;
+use serde::Deserialize;
+use serde_json::json;
+use std::cmp::Reverse;
+
+/// Most jobs a single `GET /jobs` returns.
+const MAX_JOBS_LIMIT: usize = 1000;
+
+/// The JSON API for other tools, behind `server.api_token`.
+pub fn routes(state: HttpState) -> Router<HttpState> {
+    Router::new()
+        .route("/jobs", get(list_jobs_handler))
+        .route("/jobs/{id}", get(get_job_handler))
+        .route("/profiles", get(list_profiles_handler))
+        .route("/profiles/{name}/pause", post(pause_profile_handler))
+        .route("/profiles/{name}/resume", post(resume_profile_handler))
+        .route("/seen/{id}", post(mark_seen_handler).delete(unmark_seen_handler))
+        .route("/notify/test", post(test_notification_handler))
+        .merge(stream_controller::routes())
+        .route_layer(middleware::from_fn_with_state(state, require_token))
+}
+
+pub async fn require_token(State(state): State<HttpState>, request: Request, next: Next) -> Response {
+    let given = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match given {
+        Some(token) if token_matches(token, state.config.api_token.expose()) => next.run(request).await,
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            Json(json!({ "error": "missing or invalid bearer token" })),
+        )
+            .into_response(),
+    }
+}
+
+/// Compares in constant time so the token can't be guessed byte by byte.
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn not_found(what: &str, id: &str) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({ "error": format!("unknown {} `{}`", what, id) })),
+    )
+        .into_response()
+}
+
+/// Kept apart from [`JobFilter`] because `serde(flatten)` can't parse
+/// numbers out of a query string.
+#[derive(Deserialize)]
+struct Page {
+    limit: Option<usize>,
+    #[serde(default)]
+    offset: usize,
+}
+
+/// Jobs from the history store, newest first.
+async fn list_jobs_handler(
+    State(state): State<HttpState>,
+    Query(filter): Query<JobFilter>,
+    Query(page): Query<Page>,
+) -> impl IntoResponse {
+    let mut jobs = state.app.job_history();
+    jobs.retain(|record| filter.matches(record));
+    jobs.sort_by_key(|record| Reverse(record.first_seen));
+
+    let total = jobs.len();
+    let limit = page.limit.unwrap_or(100).min(MAX_JOBS_LIMIT);
+    let jobs: Vec<_> = jobs.into_iter().skip(page.offset).take(limit).collect();
+    Json(json!({ "total": total, "jobs": jobs }))
+}
+
+async fn get_job_handler(State(state): State<HttpState>, Path(id): Path<String>) -> Response {
+    let Some(record) = state.app.job_record(&id) else {
+        return not_found("job", &id);
+    };
+    let notification = state.app.notification_state(&id);
+    let seen = notification == Some(NotificationState::Sent);
+    Json(json!({ "job": record, "seen": seen, "notification": notification })).into_response()
+}
+
+async fn list_profiles_handler(State(state): State<HttpState>) -> impl IntoResponse {
+    Json(json!({
+        "paused": state.app.is_paused(),
+        "profiles": state.app.profile_stats(),
+    }))
+}
+
+async fn pause_profile_handler(State(state): State<HttpState>, Path(name): Path<String>) -> Response {
+    set_profile_paused(&state, &name, true)
+}
+
+async fn resume_profile_handler(State(state): State<HttpState>, Path(name): Path<String>) -> Response {
+    set_profile_paused(&state, &name, false)
+}
+
+fn set_profile_paused(state: &HttpState, name: &str, paused: bool) -> Response {
+    if !state.app.set_profile_paused(name, paused) {
+        return not_found("profile", name);
+    }
+    info!("Profile {} {} through the API", name, if paused { "paused" } else { "resumed" });
+    Json(json!({ "profile": name, "paused": paused })).into_response()
+}
+
+/// Marks a job as already seen so it never triggers a notification.
+async fn mark_seen_handler(State(state): State<HttpState>, Path(id): Path<String>) -> impl IntoResponse {
+    let added = state.app.add_seen_job(id.clone());
+    if added {
+        SEEN_JOBS.inc();
+    }
+    Json(json!({ "id": id, "seen": true, "changed": added }))
+}
+
+/// Forgets a job so it is notified again once its result page next changes.
+async fn unmark_seen_handler(State(state): State<HttpState>, Path(id): Path<String>) -> Response {
+    if !state.app.remove_seen_job(&id) {
+        return not_found("seen job", &id);
+    }
+    SEEN_JOBS.dec();
+    Json(json!({ "id": id, "seen": false, "changed": true })).into_response()
+}
+
+/// Queues a message through the regular notification path.
+async fn test_notification_handler(State(state): State<HttpState>) -> Response {
+    let text = "Test notification requested through the API.".to_string();
+    match state.notifications.send(Notification::Alert(text)).await {
+        Ok(()) => (StatusCode::ACCEPTED, Json(json!({ "queued": true }))).into_response(),
+        Err(e) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "error": format!("notification queue is closed: {}", e) })),
+        )
+            .into_response(),
+    }
+}
@@ -0,0 +1,149 @@
+use axum::{
+    extract::{Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use crate::controllers::api_controller::require_token;
+use crate::controllers::http_controller::HttpState;
+use log::info;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+const DASHBOARD_PAGE: &str = include_str!("dashboard.html");
+
+/// Most rows a single seen-job search returns.
+const MAX_SEARCH_LIMIT: usize = 1000;
+
+/// Sent by the dashboard page with every control request. Browsers won't add
+/// a custom header to a cross-origin request without a CORS preflight, which
+/// this server never approves.
+const CONTROL_HEADER: &str = "x-requested-by";
+
+/// The embedded dashboard page and the JSON endpoints it polls. With
+/// `server.api_token` set the endpoints need it too; the page itself holds
+/// no data and asks for the token.
+pub fn routes(state: HttpState) -> Router<HttpState> {
+    let protected = !state.config.api_token.expose().is_empty();
+    let mut data = Router::new()
+        .route("/dashboard/status", get(status_handler))
+        .route("/dashboard/jobs", get(jobs_handler))
+        .merge(
+            Router::new()
+                .route("/dashboard/pause", post(pause_handler))
+                .route("/dashboard/resume", post(resume_handler))
+                .route_layer(middleware::from_fn(require_same_origin)),
+        );
+    if protected {
+        data = data.route_layer(middleware::from_fn_with_state(state, require_token));
+    }
+
+    Router::new().route("/", get(page_handler)).merge(data)
+}
+
+/// Keeps other web pages the operator has open from pausing the monitor.
+async fn require_same_origin(request: Request, next: Next) -> Response {
+    let headers = request.headers();
+    let host = headers.get(header::HOST).and_then(|value| value.to_str().ok());
+    let origin_matches = match headers.get(header::ORIGIN).map(|value| value.to_str()) {
+        None => true,
+        Some(Ok(origin)) => {
+            let origin = origin.strip_prefix("http://").or_else(|| origin.strip_prefix("https://"));
+            origin.is_some() && origin == host
+        }
+        Some(Err(_)) => false,
+    };
+    if !origin_matches || !headers.contains_key(CONTROL_HEADER) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(json!({ "error": "control requests must come from the dashboard page" })),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+async fn page_handler() -> impl IntoResponse {
+    Html(DASHBOARD_PAGE)
+}
+
+async fn status_handler(State(state): State<HttpState>) -> impl IntoResponse {
+    let app = &state.app;
+    let recent_jobs = app.recent_jobs(state.config.dashboard_recent_jobs);
+    let seen_jobs = app.seen_jobs.len();
+
+    Json(json!({
+        "paused": app.is_paused(),
+        "last_successful_fetch": app.health.last_successful_fetch().map(|at| at.to_rfc3339()),
+        "notifier_reachable": app.health.notifier_reachable(),
+        "seen_jobs": seen_jobs,
+        "profiles": app.profile_stats(),
+        "recent_jobs": recent_jobs,
+        "notifications": app.notification_history(),
+    }))
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    #[serde(default)]
+    q: String,
+    limit: Option<usize>,
+    #[serde(default)]
+    offset: usize,
+}
+
+/// Searches the seen-job store. IDs without a history record (seen before
+/// the history file existed) only match on the ID itself.
+async fn jobs_handler(State(state): State<HttpState>, Query(query): Query<SearchQuery>) -> impl IntoResponse {
+    let needle = query.q.trim().to_lowercase();
+    let matches = |text: &str| text.to_lowercase().contains(&needle);
+
+    let seen = &state.app.seen_jobs;
+    let mut records = state.app.job_history();
+    let with_record: HashSet<String> = records.iter().map(|record| record.job.id.clone()).collect();
+    records.retain(|record| seen.contains(&record.job.id));
+    records.retain(|record| {
+        let job = &record.job;
+        matches(&job.id)
+            || matches(&job.title)
+            || matches(&job.location)
+            || matches(&job.job_type)
+    });
+    records.sort_by_key(|record| Reverse(record.first_seen));
+
+    let mut bare_ids = Vec::new();
+    seen.for_each(|id| {
+        if matches(id) && !with_record.contains(id) {
+            bare_ids.push(id.to_string());
+        }
+    });
+    bare_ids.sort();
+
+    let total = records.len() + bare_ids.len();
+    let limit = query.limit.unwrap_or(100).min(MAX_SEARCH_LIMIT);
+    let rows: Vec<Value> = records
+        .iter()
+        .map(|record| serde_json::to_value(record).unwrap_or(Value::Null))
+        .chain(bare_ids.into_iter().map(|id| json!({ "id": id })))
+        .skip(query.offset)
+        .take(limit)
+        .collect();
+
+    Json(json!({ "total": total, "jobs": rows }))
+}
+
+async fn pause_handler(State(state): State<HttpState>) -> impl IntoResponse {
+    state.app.set_paused(true);
+    info!("Polling paused from the dashboard");
+    Json(json!({ "paused": true }))
+}
+
+async fn resume_handler(State(state): State<HttpState>) -> impl IntoResponse {
+    state.app.set_paused(false);
+    info!("Polling resumed from the dashboard");
+    Json(json!({ "paused": false }))
+}
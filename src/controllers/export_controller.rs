@@ -0,0 +1,41 @@
+use anyhow::Result;
+use crate::cli::ExportArgs;
+use crate::config::Config;
+use crate::model::ExportColumn;
+use crate::services::export_service::{ExportOptions, ExportService};
+use crate::services::persistence_service::PersistenceService;
+use log::info;
+use std::path::PathBuf;
+
+/// Runs the `export` command against the persisted job history.
+pub fn run_export(args: ExportArgs, config: &Config) -> Result<()> {
+    let mut options = ExportOptions::from_config(&config.export)?;
+    if let Some(format) = args.format {
+        options.format = format;
+    }
+    if let Some(out) = args.out {
+        options.dir = PathBuf::from(out);
+    }
+    if !args.columns.is_empty() {
+        options.columns = ExportColumn::parse_list(&args.columns).map_err(anyhow::Error::msg)?;
+    }
+    if args.no_partition {
+        options.partition_by_day = false;
+    }
+    let filter = &mut options.filter;
+    filter.location = args.location.or(filter.location.take());
+    filter.job_type = args.job_type.or(filter.job_type.take());
+    filter.min_pay = args.min_pay.or(filter.min_pay);
+    filter.since = args.since.or(filter.since);
+
+    let history = PersistenceService::load_job_history(&config.persistence.history_file)?;
+    let records: Vec<_> = history.into_values().collect();
+    let written = ExportService::export(&records, &options)?;
+
+    let total: usize = written.iter().map(|(_, rows)| rows).sum();
+    for (path, rows) in &written {
+        info!("Wrote {} jobs to {}", rows, path.display());
+    }
+    info!("Exported {} of {} jobs", total, records.len());
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use anyhow::{Context, Result};
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+use chrono::Utc;
+use crate::config::ServerConfig;
+use crate::controllers::{api_controller, dashboard_controller};
+use crate::metrics;
+use crate::model::{AppState, Notification};
+use async_channel::Sender;
+use log::info;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+#[derive(Clone)]
+pub struct HttpState {
+    pub app: Arc<AppState>,
+    pub config: ServerConfig,
+    pub notifications: Sender<Notification>,
+}
+
+pub async fn run_http_server(
+    config: ServerConfig,
+    state: Arc<AppState>,
+    notifications: Sender<Notification>,
+) -> Result<()> {
+    let bind = config.bind.clone();
+    let api_enabled = !config.api_token.expose().is_empty();
+    let state = HttpState { app: state, config, notifications };
+
+    let mut app = Router::new()
+        .route("/metrics", get(metrics_handler))
+        .route("/healthz", get(healthz_handler))
+        .route("/readyz", get(readyz_handler))
+        .merge(dashboard_controller::routes(state.clone()));
+    if api_enabled {
+        app = app.merge(api_controller::routes(state.clone()));
+    } else {
+        info!("server.api_token is not set, the JSON API is disabled");
+    }
+    let app = app.with_state(state);
+
+    let listener = TcpListener::bind(&bind)
+        .await
+        .with_context(|| format!("Failed to bind HTTP server to {}", bind))?;
+    info!("HTTP server listening on {}", bind);
+
+    axum::serve(listener, app)
+        .await
+        .with_context(|| "HTTP server stopped")
+}
+
+async fn metrics_handler() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics::render(),
+    )
+}
+
+/// Alive as long as every background task is still running.
+async fn healthz_handler(State(state): State<HttpState>) -> impl IntoResponse {
+    let tasks = state.app.health.tasks();
+    let healthy = tasks.values().all(|task| task.running);
+    let status = if healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+
+    (
+        status,
+        Json(json!({
+            "status": if healthy { "ok" } else { "unhealthy" },
+            "tasks": tasks,
+        })),
+    )
+}
+
+/// Ready once we have fetched successfully within the configured window and
+/// Telegram answered the last time we talked to it.
+async fn readyz_handler(State(state): State<HttpState>) -> impl IntoResponse {
+    let health = &state.app.health;
+    let mut problems = Vec::new();
+
+    let last_fetch = health.last_successful_fetch();
+    match last_fetch {
+        None => problems.push("no successful fetch yet".to_string()),
+        Some(at) => {
+            let age = (Utc::now() - at).num_seconds();
+            if age > state.config.ready_max_fetch_age_secs as i64 {
+                problems.push(format!("last successful fetch was {}s ago", age));
+            }
+        }
+    }
+    if !health.notifier_reachable() {
+        problems.push("Telegram is not reachable".to_string());
+    }
+
+    let ready = problems.is_empty();
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+
+    (
+        status,
+        Json(json!({
+            "status": if ready { "ready" } else { "not ready" },
+            "last_successful_fetch": last_fetch.map(|at| at.to_rfc3339()),
+            "notifier_reachable": health.notifier_reachable(),
+            "problems": problems,
+        })),
+    )
+}
@@ -1,78 +1,124 @@
 use anyhow::Result;
-use crate::config::Config;
-use crate::model::{AppState, JobInfo, NotificationBatch};
+use crate::config::{Config, SearchProfile};
+use crate::model::{AppState, JobInfo, Notification, NotificationBatch};
 use crate::services::{
-    amazon_service::AmazonService, 
+    amazon_service::{AmazonService, FetchError},
+    export_service::{ExportOptions, ExportService},
     notification_service::NotificationService,
-    persistence_service::PersistenceService,
+    report_service::ReportService,
+    scheduler_service::{PollOutcome, Scheduler},
+    supervisor_service::Supervisor,
     telegram_service::TelegramService,
     shutdown_service::ShutdownHandle,
 };
-use log::{info, warn};
+use crate::metrics::NEW_JOBS;
+use crate::utils::format_pay;
+use log::{debug, info, warn};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::time::{self, Duration, MissedTickBehavior};
+use tokio::sync::Semaphore;
+use tracing::{info_span, Instrument, Span};
 
 pub async fn run_job_monitor(
     config: Config,
+    state: Arc<AppState>,
+    supervisor: Supervisor,
+    notification_service: Arc<NotificationService>,
     shutdown_handle: ShutdownHandle,
 ) -> Result<()> {
     // Initialize services
-    let amazon_service = AmazonService::new(config.clone());
     let telegram_service = TelegramService::new(config.clone());
-    let notification_service = NotificationService::new();
     let notification_sender = notification_service.sender();
-    
+    let amazon_service = AmazonService::new(config.clone(), notification_sender.clone());
+
     // Start notification worker
-    tokio::spawn({
-        let telegram_service = telegram_service.clone();
-        async move {
-            notification_service.run(telegram_service).await;
+    supervisor.supervise("notifications", {
+        let state = state.clone();
+        move || {
+            let notification_service = notification_service.clone();
+            let telegram_service = telegram_service.clone();
+            let state = state.clone();
+            async move {
+                notification_service.run(telegram_service, &state).await;
+            }
         }
     });
 
-    // Load state
-    let initial_jobs = PersistenceService::load_seen_jobs(&config.persistence.seen_jobs_file)?;
-    info!("Loaded {} seen jobs", initial_jobs.len());
-    
-    let state = Arc::new(AppState::new(initial_jobs));
-    
-    // Start persistence service
-    tokio::spawn({
+    // Start the continuous export sink
+    if config.export.sink_enabled {
+        let options = Arc::new(ExportOptions::from_config(&config.export)?);
         let state = state.clone();
-        let config = config.clone();
-        async move {
-            PersistenceService::run(state, config).await;
-        }
-    });
+        supervisor.supervise("export_sink", move || ExportService::run_sink(state.clone(), options.clone()));
+    }
+
+    // Start the weekly report
+    if config.report.weekly_digest {
+        let state = state.clone();
+        let report_config = config.report.clone();
+        let notification_sender = notification_sender.clone();
+        supervisor.supervise("weekly_report", move || {
+            ReportService::run_weekly(state.clone(), report_config.clone(), notification_sender.clone())
+        });
+    }
 
-    // Start processing loop
+    // Start processing loop. Polls fire on the scheduler's cadence rather
+    // than waiting for each other, with at most `requests_per_second` in flight.
+    // Profiles take turns.
+    let profiles: Vec<Arc<SearchProfile>> = config.profiles().into_iter().map(Arc::new).collect();
+    for profile in &profiles {
+        state.register_profile(&profile.name);
+    }
+    let mut next_profile = profiles.iter().cycle();
     let amazon_service = Arc::new(amazon_service);
-    let mut interval = time::interval(Duration::from_secs(1));
-    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let scheduler = Arc::new(Scheduler::new(&config));
+    let in_flight = Arc::new(Semaphore::new(config.rate_limiting.requests_per_second));
 
     while !shutdown_handle.is_shutdown() {
-        interval.tick().await;
+        tokio::time::sleep(scheduler.next_delay()).await;
+        if state.is_paused() {
+            continue;
+        }
 
-        // Process requests with rate limiting
-        let mut tasks = Vec::with_capacity(config.rate_limiting.requests_per_second);
-        for i in 0..config.rate_limiting.requests_per_second {
-            let delay = Duration::from_millis(i as u64 * config.rate_limiting.delay_between_requests_ms);
-            let amazon_service = amazon_service.clone();
-            let state = state.clone();
-            let notification_sender = notification_sender.clone();
+        let Ok(permit) = in_flight.clone().try_acquire_owned() else {
+            debug!("All request slots busy, skipping poll");
+            continue;
+        };
+        let Some(profile) = next_profile
+            .by_ref()
+            .take(profiles.len())
+            .find(|profile| !state.is_profile_paused(&profile.name))
+            .cloned()
+        else {
+            debug!("Every profile is paused, skipping poll");
+            continue;
+        };
+        let amazon_service = amazon_service.clone();
+        let state = state.clone();
+        let notification_sender = notification_sender.clone();
+        let scheduler = scheduler.clone();
 
-            tasks.push(tokio::spawn(async move {
-                tokio::time::sleep(delay).await;
-                if let Err(e) = process_request(&amazon_service, &state, &notification_sender).await {
+        let span = info_span!(
+            "poll",
+            profile = %profile.name,
+            jobs = tracing::field::Empty,
+            new_jobs = tracing::field::Empty
+        );
+        tokio::spawn(async move {
+            let outcome = match process_request(&amazon_service, &state, &profile, &notification_sender).await {
+                Ok(false) => PollOutcome::Unchanged,
+                Ok(true) => PollOutcome::Changed,
+                Err(e) => {
                     warn!("Request processing failed: {}", e);
+                    state.record_poll_error(&profile.name, e.to_string());
+                    match e.downcast_ref::<FetchError>() {
+                        Some(e) if e.is_throttling() => PollOutcome::Throttled(e.retry_after()),
+                        _ => PollOutcome::Failed,
+                    }
                 }
-            }));
-        }
-
-        for task in tasks {
-            let _ = task.await;
-        }
+            };
+            scheduler.record(outcome);
+            drop(permit);
+        }.instrument(span));
     }
 
     info!("Shutting down job monitor");
@@ -82,49 +128,47 @@ pub async fn run_job_monitor(
 async fn process_request(
     amazon_service: &Arc<AmazonService>,
     state: &Arc<AppState>,
-    notification_sender: &async_channel::Sender<NotificationBatch>,
-) -> Result<()> {
-    let jobs = amazon_service.fetch_jobs(state).await?;
-    if jobs.is_empty() {
-        return Ok(());
-    }
+    profile: &SearchProfile,
+    notification_sender: &async_channel::Sender<Notification>,
+) -> Result<bool> {
+    let Some(jobs) = amazon_service.fetch_jobs(state, profile).await? else {
+        state.close_delisted_jobs();
+        return Ok(false);
+    };
+    Span::current().record("jobs", jobs.len());
+    state.record_listed_jobs(&profile.name, &jobs);
 
-    // Group new jobs by location
+    // Claim the whole result set at once, then group the new jobs by location
+    let new_jobs = state.claim_new_jobs(&profile.name, &jobs);
     let mut new_jobs_by_location: HashMap<String, Vec<JobInfo>> = HashMap::new();
-    let mut new_jobs_count = 0;
-
-    for job in jobs {
-        if state.add_seen_job(job.id.clone()).await {
-            new_jobs_by_location
-                .entry(job.location.clone())
-                .or_default()
-                .push(job.clone());
-                
-            new_jobs_count += 1;
-            
-            // Log to console immediately
-            log::info!(
-                "- {} @ {} @ {} (${:.2}-${:.2}/hr)",
-                job.title,
-                job.location,
-                job.job_type,
-                job.pay_min,
-                job.pay_max
-            );
-        }
+    for job in &new_jobs {
+        log::info!(
+            "- {} @ {} @ {} ({})",
+            job.title,
+            job.location,
+            job.job_type,
+            format_pay(job.pay_min, job.pay_max)
+        );
+        new_jobs_by_location.entry(job.location.clone()).or_default().push(job.clone());
     }
 
+    let new_jobs_count = new_jobs.len();
+    Span::current().record("new_jobs", new_jobs_count);
     if new_jobs_count > 0 {
         log::info!("Found {} new jobs", new_jobs_count);
-        
-        // Send notifications in batches per location
+        NEW_JOBS.with_label_values(&[profile.name.as_str()]).inc_by(new_jobs_count as u64);
+
+        // Send notifications in batches per location. The jobs only count as
+        // seen once the notification worker has delivered them.
         for (location, jobs) in new_jobs_by_location {
+            let ids: Vec<String> = jobs.iter().map(|job| job.id.clone()).collect();
             let batch = NotificationBatch { location, jobs };
-            if let Err(e) = notification_sender.send(batch).await {
+            if let Err(e) = notification_sender.send(Notification::Jobs(batch)).await {
                 log::error!("Failed to send notification batch: {}", e);
+                state.release_jobs(&ids);
             }
         }
     }
 
-    Ok(())
+    Ok(true)
 }
\ No newline at end of file
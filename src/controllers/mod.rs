@@ -1 +1,7 @@
-pub mod job_monitor_controller;
\ No newline at end of file
+pub mod api_controller;
+pub mod dashboard_controller;
+pub mod export_controller;
+pub mod http_controller;
+pub mod job_monitor_controller;
+pub mod report_controller;
+pub mod stream_controller;
\ No newline at end of file
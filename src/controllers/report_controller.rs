@@ -0,0 +1,20 @@
+use anyhow::Result;
+use chrono::Local;
+use crate::cli::ReportArgs;
+use crate::config::Config;
+use crate::services::persistence_service::PersistenceService;
+use crate::services::report_service::Report;
+
+/// Runs the `report` command against the persisted job history.
+pub fn run_report(args: ReportArgs, config: &Config) -> Result<()> {
+    let history = PersistenceService::load_job_history(&config.persistence.history_file)?;
+    let records: Vec<_> = history.into_values().collect();
+    let report = Report::build(&records, Local::now(), args.days.unwrap_or(config.report.days));
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", report.render(None));
+    }
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+    routing::get,
+    Router,
+};
+use crate::controllers::http_controller::HttpState;
+use crate::model::{JobEvent, JobFilter};
+use futures_util::stream::{self, StreamExt};
+use log::{debug, warn};
+use serde::Deserialize;
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
+
+pub fn routes() -> Router<HttpState> {
+    Router::new().route("/events", get(events_handler))
+}
+
+/// Kept apart from [`JobFilter`] for the same reason as the `/jobs` paging.
+#[derive(Deserialize)]
+struct StreamQuery {
+    /// Comma-separated event kinds to send, e.g. `new,closed`. All by default.
+    kinds: Option<String>,
+    /// For clients that can't set the `Last-Event-ID` header.
+    last_event_id: Option<u64>,
+}
+
+/// Streams job events as they happen. A client that reconnects with
+/// `Last-Event-ID` first gets whatever it missed, as far back as the event
+/// backlog goes.
+async fn events_handler(
+    State(state): State<HttpState>,
+    headers: HeaderMap,
+    Query(filter): Query<JobFilter>,
+    Query(query): Query<StreamQuery>,
+) -> impl IntoResponse {
+    let last_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .or(query.last_event_id);
+    let kinds: Option<Vec<String>> = query
+        .kinds
+        .map(|kinds| kinds.split(',').map(|kind| kind.trim().to_lowercase()).collect());
+
+    let (backlog, receiver) = state.app.events.subscribe(last_id);
+    debug!("Stream client connected, replaying {} events", backlog.len());
+
+    let live = stream::unfold(receiver, |mut receiver| async move {
+        match receiver.recv().await {
+            Ok(event) => Some((event, receiver)),
+            // Ending the stream makes the client reconnect and catch up from
+            // the backlog instead of silently missing events
+            Err(RecvError::Lagged(missed)) => {
+                warn!("Stream client fell {} events behind, disconnecting it", missed);
+                None
+            }
+            Err(RecvError::Closed) => None,
+        }
+    });
+
+    let events = stream::iter(backlog)
+        .chain(live)
+        .filter(move |event| {
+            let wanted = kinds.as_ref().is_none_or(|kinds| kinds.iter().any(|kind| kind == event.kind.as_str()))
+                && filter.matches(&event.job);
+            std::future::ready(wanted)
+        })
+        .map(to_sse);
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+fn to_sse(event: JobEvent) -> Result<Event, Infallible> {
+    let sse = Event::default().id(event.id.to_string()).event(event.kind.as_str());
+    Ok(sse.json_data(&event).unwrap_or_else(|e| {
+        warn!("Failed to encode job event {}: {}", event.id, e);
+        Event::default().comment("unencodable event")
+    }))
+}
+
@@ -1,36 +1,62 @@
-use chrono::Local;
-use log::{Level, LevelFilter, Metadata, Record, SetLoggerError};
-use colored::*;
+use anyhow::{Context, Result};
+use crate::config::{LogFormat, LogRotation, LoggingConfig};
+use std::env;
+use std::io::{self, IsTerminal};
+use tracing::Subscriber;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::{self, time::ChronoLocal, MakeWriter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
 
-struct LocalTimeLogger;
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
 
-impl log::Log for LocalTimeLogger {
-    fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= Level::Info
-    }
+/// Installs the global subscriber. `RUST_LOG` overrides `logging.level`.
+/// Records from the `log` crate are forwarded, so both macro families end up
+/// in the same place.
+pub fn init_logger(config: &LoggingConfig) -> Result<()> {
+    let directives = env::var("RUST_LOG")
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| config.level.clone());
+    let filter = EnvFilter::try_new(&directives)
+        .with_context(|| format!("Invalid log filter `{}`", directives))?;
 
-    fn log(&self, record: &Record) {
-        if self.enabled(record.metadata()) {
-            let now = Local::now().format("%Y-%m-%d %H:%M:%S");
-            let level = record.level();
-            let message = record.args();
-            
-            let colored_level = match level {
-                Level::Error => level.to_string().red(),
-                Level::Warn => level.to_string().yellow(),
-                Level::Info => level.to_string().cyan(),
-                Level::Debug => level.to_string().purple(),
-                Level::Trace => level.to_string().normal(),
-            };
-            
-            println!("{} [{}] - {}", now, colored_level, message);
-        }
+    let mut layers = vec![fmt_layer(config.format, io::stdout, io::stdout().is_terminal())];
+    if let Some(dir) = &config.file_dir {
+        let rotation = match config.file_rotation {
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        };
+        let appender = RollingFileAppender::builder()
+            .rotation(rotation)
+            .filename_prefix(&config.file_prefix)
+            .build(dir)
+            .with_context(|| format!("Failed to open log files in {}", dir))?;
+        layers.push(fmt_layer(config.format, appender, false));
     }
 
-    fn flush(&self) {}
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(layers)
+        .try_init()
+        .with_context(|| "Failed to install logger")
 }
 
-pub fn init_logger() -> Result<(), SetLoggerError> {
-    log::set_boxed_logger(Box::new(LocalTimeLogger))
-        .map(|()| log::set_max_level(LevelFilter::Info))
-}
\ No newline at end of file
+fn fmt_layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi)
+        .with_timer(ChronoLocal::new(TIME_FORMAT.to_string()));
+
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().with_current_span(true).with_span_list(true).boxed(),
+    }
+}
@@ -1,36 +1,140 @@
+mod cli;
 mod config;
 mod model;
 mod services;
 mod controllers;
 mod logging;
+mod metrics;
+mod seen_set;
 mod utils;
 
 use anyhow::Result;
-use config::Config;
+use clap::Parser;
+use cli::{Cli, Command};
+use config::{Config, Scope};
+use controllers::export_controller::run_export;
+use controllers::http_controller::run_http_server;
+use controllers::report_controller::run_report;
 use controllers::job_monitor_controller::run_job_monitor;
+use log::info;
+use metrics::SEEN_JOBS;
+use model::AppState;
+use services::notification_service::NotificationService;
+use services::persistence_service::PersistenceService;
 use services::shutdown_service::ShutdownService;
+use services::supervisor_service::Supervisor;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    // Load configuration, checking only what the command needs
+    let scope = match cli.command {
+        Some(Command::Export(_) | Command::Report(_)) => Scope::LocalData,
+        None => Scope::Monitor,
+    };
+    let config = Config::load(scope)?;
+
     // Initialize logger
-    logging::init_logger();
+    logging::init_logger(&config.logging)?;
+    for notice in &config.notices {
+        log::warn!("{}", notice);
+    }
+
+    match cli.command {
+        Some(Command::Export(args)) => run_export(args, &config),
+        Some(Command::Report(args)) => run_report(args, &config),
+        None => run_monitor(config).await,
+    }
+}
+
+async fn run_monitor(config: Config) -> Result<()> {
+    // Load state
+    let (initial_jobs, delivered) = PersistenceService::load_seen_jobs(
+        &config.persistence.seen_jobs_file,
+        &config.persistence.journal_file,
+    )?;
+    info!("Loaded {} seen jobs", initial_jobs.len());
+    SEEN_JOBS.set(initial_jobs.len() as i64);
+
+    let history = PersistenceService::load_job_history(&config.persistence.history_file)?;
+    info!("Loaded {} job history records", history.len());
 
-    // Load configuration
-    let config = Config::load()?;
+    let close_after = chrono::Duration::from_std(Duration::from_secs(config.persistence.close_after_secs))
+        .unwrap_or(chrono::Duration::MAX);
+    let state = Arc::new(AppState::new(initial_jobs, history, close_after));
+    state.restore_deliveries(delivered);
 
     // Setup shutdown service
     let shutdown_service = ShutdownService::new();
     let shutdown_handle = shutdown_service.handle();
 
-    // Start job monitor
-    tokio::spawn(async move {
-        if let Err(e) = run_job_monitor(config, shutdown_handle.clone()).await {
-            log::error!("Job monitor failed: {}", e);
-        }
+    // Setup notifications and the supervisor that restarts background tasks
+    let notification_service = Arc::new(NotificationService::new(
+        config.subscribers(),
+        Duration::from_millis(config.telegram.coalesce_window_ms),
+    ));
+    let supervisor = Supervisor::new(
+        state.clone(),
+        config.supervisor.clone(),
+        notification_service.sender(),
+        shutdown_handle.clone(),
+    );
+
+    // Start persistence here rather than with the monitor, so shutdown can
+    // wait for its final write
+    let persistence = supervisor.supervise("persistence", {
+        let state = state.clone();
+        let config = config.clone();
+        move || PersistenceService::run(state.clone(), config.clone())
     });
 
-    // Wait for shutdown signal
-    shutdown_service.wait_for_shutdown().await?;
+    // Start HTTP server
+    metrics::init();
+    if config.server.enabled {
+        let server_config = config.server.clone();
+        let state = state.clone();
+        let notifications = notification_service.sender();
+        supervisor.supervise("http_server", move || {
+            let server_config = server_config.clone();
+            let state = state.clone();
+            let notifications = notifications.clone();
+            async move {
+                if let Err(e) = run_http_server(server_config, state, notifications).await {
+                    log::error!("HTTP server failed: {:#}", e);
+                }
+            }
+        });
+    }
+
+    // Start job monitor
+    let monitor = supervisor.spawn(
+        "job_monitor",
+        run_job_monitor(config, state.clone(), supervisor.clone(), notification_service, shutdown_handle),
+    );
+
+    // Run until a shutdown signal, or exit non-zero if the monitor dies so an
+    // orchestrator can restart us
+    let result = tokio::select! {
+        result = shutdown_service.wait_for_shutdown() => result,
+        result = monitor => match result {
+            Ok(Ok(())) => {
+                info!("Job monitor stopped");
+                Ok(())
+            }
+            Ok(Err(e)) => Err(e.context("Job monitor failed")),
+            Err(e) => Err(anyhow::anyhow!("Job monitor panicked: {}", e)),
+        },
+    };
 
-    Ok(())
-}
\ No newline at end of file
+    // Either way, let persistence write out what's left before exiting
+    shutdown_service.shut_down();
+    state.shutdown_flag.store(true, Ordering::Relaxed);
+    if let Err(e) = persistence.await {
+        log::error!("Persistence failed while shutting down: {}", e);
+    }
+    result
+}
@@ -0,0 +1,184 @@
+use prometheus::{
+    register_gauge, register_histogram, register_histogram_vec, register_int_counter,
+    register_int_counter_vec, register_int_gauge, register_int_gauge_vec, Encoder, Gauge,
+    Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+};
+use std::sync::LazyLock;
+
+pub static POLL_INTERVAL_SECONDS: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "joblog_poll_interval_seconds",
+        "Delay between polls currently chosen by the scheduler"
+    )
+    .expect("metric can be registered")
+});
+
+pub static SCHEDULER_BACKOFFS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "joblog_scheduler_backoffs_total",
+        "Global backoffs triggered by throttling or server errors"
+    )
+    .expect("metric can be registered")
+});
+
+pub static HOT_HOURS_ACTIVE: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "joblog_hot_hours_active",
+        "1 while the scheduler is inside configured hot hours"
+    )
+    .expect("metric can be registered")
+});
+
+pub static RESPONSES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "joblog_responses_total",
+        "Result pages fetched per profile, split by whether they changed",
+        &["profile", "result"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static FETCHES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "joblog_fetches_total",
+        "Amazon API requests per profile by outcome (success or error class)",
+        &["profile", "outcome"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static FETCH_RETRIES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "joblog_fetch_retries_total",
+        "Amazon API requests retried, by the error class that caused the retry",
+        &["class"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static FETCH_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "joblog_fetch_duration_seconds",
+        "Latency of single Amazon API requests per profile",
+        &["profile"],
+        vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]
+    )
+    .expect("metric can be registered")
+});
+
+pub static LAST_SUCCESSFUL_FETCH: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "joblog_last_successful_fetch_timestamp_seconds",
+        "Unix time of the last successful Amazon API request"
+    )
+    .expect("metric can be registered")
+});
+
+pub static JOBS_LISTED: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "joblog_jobs_listed",
+        "Jobs on the latest result page per profile",
+        &["profile"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static NEW_JOBS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "joblog_new_jobs_total",
+        "Jobs seen for the first time, per profile",
+        &["profile"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static NOTIFICATIONS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "joblog_notifications_total",
+        "Notifications handed to Telegram by kind and result",
+        &["kind", "result"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static NOTIFICATION_DURATION: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "joblog_notification_duration_seconds",
+        "Latency of Telegram sends",
+        vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]
+    )
+    .expect("metric can be registered")
+});
+
+pub static NOTIFICATION_QUEUE_DEPTH: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "joblog_notification_queue_depth",
+        "Notifications waiting for the Telegram worker"
+    )
+    .expect("metric can be registered")
+});
+
+pub static SEEN_JOBS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "joblog_seen_jobs",
+        "Job IDs in the seen set"
+    )
+    .expect("metric can be registered")
+});
+
+pub static TASK_RESTARTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "joblog_task_restarts_total",
+        "Background tasks restarted by the supervisor after a panic or exit",
+        &["task"]
+    )
+    .expect("metric can be registered")
+});
+
+pub static PROXIES_AVAILABLE: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "joblog_proxies_available",
+        "Egress proxies in the pool that aren't currently ejected"
+    )
+    .expect("metric can be registered")
+});
+
+pub static PROXY_EJECTIONS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "joblog_proxy_ejections_total",
+        "Times a proxy was taken out of the pool after failing repeatedly",
+        &["proxy"]
+    )
+    .expect("metric can be registered")
+});
+
+/// Registers every metric up front so scrapes show them before their first
+/// update.
+pub fn init() {
+    LazyLock::force(&POLL_INTERVAL_SECONDS);
+    LazyLock::force(&SCHEDULER_BACKOFFS);
+    LazyLock::force(&HOT_HOURS_ACTIVE);
+    LazyLock::force(&RESPONSES);
+    LazyLock::force(&FETCHES);
+    LazyLock::force(&FETCH_RETRIES);
+    LazyLock::force(&FETCH_DURATION);
+    LazyLock::force(&LAST_SUCCESSFUL_FETCH);
+    LazyLock::force(&JOBS_LISTED);
+    LazyLock::force(&NEW_JOBS);
+    LazyLock::force(&NOTIFICATIONS);
+    LazyLock::force(&NOTIFICATION_DURATION);
+    LazyLock::force(&NOTIFICATION_QUEUE_DEPTH);
+    LazyLock::force(&SEEN_JOBS);
+    LazyLock::force(&TASK_RESTARTS);
+    LazyLock::force(&PROXIES_AVAILABLE);
+    LazyLock::force(&PROXY_EJECTIONS);
+}
+
+/// Renders everything registered above in the Prometheus text format.
+pub fn render() -> String {
+    let mut buffer = Vec::new();
+    if let Err(e) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        log::error!("Failed to encode metrics: {}", e);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
@@ -1,17 +1,21 @@
-use serde::Deserialize;
-use std::collections::HashSet;
-use std::sync::atomic::AtomicBool;
-use tokio::sync::Mutex;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use crate::seen_set::SeenSet;
+use tokio::sync::broadcast;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct JobInfo {
     pub id: String,
     pub title: String,
     pub location: String,
     pub job_type: String,
-    pub pay_min: f64,
-    pub pay_max: f64,
-    pub shift: i64,
+    pub pay_min: Option<f64>,
+    pub pay_max: Option<f64>,
+    pub shift: Option<i64>,
 }
 
 #[derive(Deserialize)]
@@ -27,8 +31,10 @@ pub struct ApiData {
 
 #[derive(Deserialize)]
 pub struct SearchJobCards {
+    /// Kept raw so each card can be parsed on its own and a malformed one
+    /// doesn't take the rest of the page down with it.
     #[serde(rename = "jobCards")]
-    pub job_cards: Vec<JobCard>,
+    pub job_cards: Vec<serde_json::Value>,
 }
 
 #[derive(Deserialize)]
@@ -36,43 +42,1019 @@ pub struct JobCard {
     #[serde(rename = "jobId")]
     pub id: String,
     #[serde(rename = "jobTitle")]
-    pub title: String,
+    pub title: Option<String>,
     #[serde(rename = "jobType")]
-    pub job_type: String,
+    pub job_type: Option<String>,
     #[serde(rename = "locationName")]
-    pub location: String,
+    pub location: Option<String>,
     #[serde(rename = "scheduleCount")]
-    pub shift: i64,
+    pub shift: Option<i64>,
     #[serde(rename = "totalPayRateMin")]
-    pub pay_min: f64,
+    pub pay_min: Option<f64>,
     #[serde(rename = "totalPayRateMax")]
-    pub pay_max: f64,
+    pub pay_max: Option<f64>,
+}
+
+/// How often a profile's result page actually changes between polls.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProfileStats {
+    pub polls: u64,
+    pub changes: u64,
+    pub last_poll: Option<DateTime<Utc>>,
+    pub last_change: Option<DateTime<Utc>>,
+    /// Why the latest poll failed, cleared by the next successful one.
+    pub last_error: Option<String>,
+    /// Skipped by the poll loop until resumed.
+    pub paused: bool,
+    #[serde(skip)]
+    last_hash: Option<u64>,
+    /// Job IDs on the latest changed page, to notice jobs that close.
+    #[serde(skip)]
+    listed: HashSet<String>,
+}
+
+/// A job we have listed at least once, kept in the history file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRecord {
+    #[serde(flatten)]
+    pub job: JobInfo,
+    /// Profile that listed the job first.
+    pub profile: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// When the job dropped off every profile's results, if it has.
+    #[serde(default)]
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobEventKind {
+    New,
+    Updated,
+    Closed,
+}
+
+impl JobEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobEventKind::New => "new",
+            JobEventKind::Updated => "updated",
+            JobEventKind::Closed => "closed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobEvent {
+    pub id: u64,
+    pub kind: JobEventKind,
+    pub at: DateTime<Utc>,
+    pub job: JobRecord,
+}
+
+/// How many past events a reconnecting stream client can catch up on.
+const EVENT_BACKLOG: usize = 1000;
+
+struct EventLog {
+    next_id: u64,
+    recent: VecDeque<JobEvent>,
+}
+
+/// Fans job events out to stream subscribers and keeps the latest ones so
+/// a client can resume from its last event ID.
+pub struct JobEvents {
+    sender: broadcast::Sender<JobEvent>,
+    log: std::sync::Mutex<EventLog>,
+}
+
+impl JobEvents {
+    fn new() -> Self {
+        let (sender, _) = broadcast::channel(256);
+        // IDs start at the boot time so they keep growing across restarts and
+        // a client resuming from before one simply gets everything we have
+        let next_id = Utc::now().timestamp_micros().max(0) as u64;
+        JobEvents {
+            sender,
+            log: std::sync::Mutex::new(EventLog { next_id, recent: VecDeque::new() }),
+        }
+    }
+
+    fn publish(&self, kind: JobEventKind, job: JobRecord) {
+        let mut log = self.log.lock().expect("event log lock poisoned");
+        let event = JobEvent { id: log.next_id, kind, at: Utc::now(), job };
+        log.next_id += 1;
+        if log.recent.len() == EVENT_BACKLOG {
+            log.recent.pop_front();
+        }
+        log.recent.push_back(event.clone());
+        // Nobody listening is fine
+        let _ = self.sender.send(event);
+    }
+
+    /// Returns the stored events after `last_id` along with a receiver for
+    /// everything published from then on, with no gap or overlap between
+    /// the two.
+    pub fn subscribe(&self, last_id: Option<u64>) -> (Vec<JobEvent>, broadcast::Receiver<JobEvent>) {
+        let log = self.log.lock().expect("event log lock poisoned");
+        let receiver = self.sender.subscribe();
+        let backlog = match last_id {
+            Some(last_id) => log.recent.iter().filter(|event| event.id > last_id).cloned().collect(),
+            None => Vec::new(),
+        };
+        (backlog, receiver)
+    }
+
+    /// A receiver for everything published from now on, with the ID to pass
+    /// to [`subscribe`](Self::subscribe) if it lags before seeing an event.
+    pub fn subscribe_from_now(&self) -> (u64, broadcast::Receiver<JobEvent>) {
+        let log = self.log.lock().expect("event log lock poisoned");
+        (log.next_id.saturating_sub(1), self.sender.subscribe())
+    }
+}
+
+/// A [`JobRecord`] field as a column in exports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportColumn {
+    Id,
+    Title,
+    Location,
+    JobType,
+    PayMin,
+    PayMax,
+    Shift,
+    Profile,
+    FirstSeen,
+    LastSeen,
+    ClosedAt,
+}
+
+impl ExportColumn {
+    pub const ALL: [ExportColumn; 11] = [
+        ExportColumn::Id,
+        ExportColumn::Title,
+        ExportColumn::Location,
+        ExportColumn::JobType,
+        ExportColumn::PayMin,
+        ExportColumn::PayMax,
+        ExportColumn::Shift,
+        ExportColumn::Profile,
+        ExportColumn::FirstSeen,
+        ExportColumn::LastSeen,
+        ExportColumn::ClosedAt,
+    ];
+
+    /// Same names as the fields of a serialized [`JobRecord`].
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportColumn::Id => "id",
+            ExportColumn::Title => "title",
+            ExportColumn::Location => "location",
+            ExportColumn::JobType => "job_type",
+            ExportColumn::PayMin => "pay_min",
+            ExportColumn::PayMax => "pay_max",
+            ExportColumn::Shift => "shift",
+            ExportColumn::Profile => "profile",
+            ExportColumn::FirstSeen => "first_seen",
+            ExportColumn::LastSeen => "last_seen",
+            ExportColumn::ClosedAt => "closed_at",
+        }
+    }
+
+    /// Parses a column selection, where an empty list means every column.
+    pub fn parse_list(names: &[String]) -> Result<Vec<ExportColumn>, String> {
+        if names.is_empty() {
+            return Ok(ExportColumn::ALL.to_vec());
+        }
+        names
+            .iter()
+            .map(|name| {
+                ExportColumn::ALL
+                    .into_iter()
+                    .find(|column| column.name() == name.trim())
+                    .ok_or_else(|| {
+                        let known: Vec<&str> = ExportColumn::ALL.iter().map(ExportColumn::name).collect();
+                        format!("unknown column `{}`, expected one of {}", name, known.join(", "))
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Narrows a job listing. Every field that is set has to match.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct JobFilter {
+    /// Case-insensitive substring of the location name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// One of the job's types, raw (`FULL_TIME`) or as written by people
+    /// (`full`, `Full time`).
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub job_type: Option<String>,
+    /// Lower bound on the best pay the job advertises.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_pay: Option<f64>,
+    /// Only jobs first seen at or after this time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl JobFilter {
+    pub fn matches(&self, record: &JobRecord) -> bool {
+        if let Some(since) = self.since
+            && record.first_seen < since
+        {
+            return false;
+        }
+        self.matches_job(&record.job)
+    }
+
+    /// Like [`JobFilter::matches`] but ignores `since`, which needs history.
+    pub fn matches_job(&self, job: &JobInfo) -> bool {
+        if let Some(location) = &self.location
+            && !job.location.to_lowercase().contains(&location.to_lowercase())
+        {
+            return false;
+        }
+        if let Some(wanted) = &self.job_type {
+            let wanted = normalize_job_type(wanted);
+            if !job.job_type.split(';').any(|part| normalize_job_type(part) == wanted) {
+                return false;
+            }
+        }
+        if let Some(min_pay) = self.min_pay
+            && !job.pay_max.or(job.pay_min).is_some_and(|pay| pay >= min_pay)
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// Reduces `FULL_TIME`, `Full time` and `full` to the same key.
+fn normalize_job_type(job_type: &str) -> String {
+    let key: String = job_type
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_lowercase();
+    match key.strip_suffix("time") {
+        Some(stripped) if !stripped.is_empty() => stripped.to_string(),
+        _ => key,
+    }
+}
+
+/// One delivery attempt, as shown on the dashboard.
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationRecord {
+    pub at: DateTime<Utc>,
+    pub kind: &'static str,
+    pub summary: String,
+    pub delivered: bool,
+    pub error: Option<String>,
+}
+
+/// How many delivery attempts the dashboard can look back on.
+const NOTIFICATION_HISTORY: usize = 200;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TaskStatus {
+    pub running: bool,
+    pub restarts: u64,
+}
+
+/// What the health and readiness endpoints report on.
+#[derive(Default)]
+pub struct Health {
+    /// Unix seconds, 0 until the first success.
+    last_successful_fetch: AtomicI64,
+    notifier_reachable: AtomicBool,
+    tasks: std::sync::Mutex<BTreeMap<&'static str, TaskStatus>>,
+}
+
+impl Health {
+    pub fn record_successful_fetch(&self) {
+        self.last_successful_fetch.store(Utc::now().timestamp(), Ordering::Relaxed);
+    }
+
+    pub fn last_successful_fetch(&self) -> Option<DateTime<Utc>> {
+        match self.last_successful_fetch.load(Ordering::Relaxed) {
+            0 => None,
+            secs => DateTime::from_timestamp(secs, 0),
+        }
+    }
+
+    pub fn set_notifier_reachable(&self, reachable: bool) {
+        self.notifier_reachable.store(reachable, Ordering::Relaxed);
+    }
+
+    pub fn notifier_reachable(&self) -> bool {
+        self.notifier_reachable.load(Ordering::Relaxed)
+    }
+
+    pub fn set_task_running(&self, name: &'static str, running: bool) {
+        self.tasks.lock().expect("health lock poisoned").entry(name).or_default().running = running;
+    }
+
+    pub fn record_task_restart(&self, name: &'static str) {
+        self.tasks.lock().expect("health lock poisoned").entry(name).or_default().restarts += 1;
+    }
+
+    /// Every background task registered so far.
+    pub fn tasks(&self) -> BTreeMap<&'static str, TaskStatus> {
+        self.tasks.lock().expect("health lock poisoned").clone()
+    }
+}
+
+/// Where a claimed job is on its way to the subscribers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationState {
+    /// Claimed by a poll, with deliveries queued or in flight.
+    Pending,
+    /// Every subscriber got the job, or won't, so it is in the seen set.
+    Sent,
+    /// A delivery failed. A poll listing the job claims it again once its
+    /// retry is due.
+    #[default]
+    Failed,
+}
+
+/// How a message carrying jobs to one subscriber ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryOutcome {
+    Delivered,
+    /// Refused for good, e.g. the chat blocked the bot, so not retried.
+    Rejected,
+    /// Might go through later, e.g. Telegram was down or throttling.
+    Failed,
+}
+
+/// Failed messages to one subscriber before a job is given up on for them.
+const MAX_DELIVERY_ATTEMPTS: u32 = 10;
+
+/// How long a job waits to be claimed again after its `failures`th failed
+/// message: not at all after the first, then doubling from 30 seconds up
+/// to half an hour.
+fn retry_delay(failures: u32) -> chrono::Duration {
+    match failures {
+        0 | 1 => chrono::Duration::zero(),
+        n => chrono::Duration::seconds(30 << (n - 2).min(6)).min(chrono::Duration::minutes(30)),
+    }
+}
+
+/// A change to the seen set waiting to be written to the journal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeenChange {
+    Added(String),
+    Removed(String),
+    /// One subscriber is done with a job the others are still waiting for,
+    /// so a restart doesn't send it to them again.
+    Delivered { job_id: String, subscriber: String },
+}
+
+/// A claimed job that isn't in the seen set yet.
+#[derive(Default)]
+struct Delivery {
+    state: NotificationState,
+    /// Subscribers with a message carrying this job queued or in flight.
+    pending: HashSet<String>,
+    /// Subscribers that got this job, refused it or ran out of attempts, so
+    /// a retry leaves them out.
+    done: HashSet<String>,
+    /// Failed messages so far, per subscriber.
+    failures: HashMap<String, u32>,
+    /// A failed job isn't claimed again before this.
+    retry_at: Option<DateTime<Utc>>,
+}
+
+impl Delivery {
+    /// Counts a failed message to `subscriber` and returns how long to wait
+    /// before the next attempt, or `None` once they're out of attempts.
+    fn record_failure(&mut self, job_id: &str, subscriber: &str) -> Option<chrono::Duration> {
+        let failures = self.failures.entry(subscriber.to_string()).or_default();
+        *failures += 1;
+        if *failures >= MAX_DELIVERY_ATTEMPTS {
+            log::warn!("Giving up on job {} for {} after {} failed messages", job_id, subscriber, failures);
+            return None;
+        }
+        Some(retry_delay(*failures))
+    }
 }
 
 pub struct AppState {
-    pub seen_jobs: Mutex<HashSet<String>>,
+    /// Jobs that were notified, or marked seen through the API. Only these
+    /// are persisted, so a job claimed but not yet delivered is picked up
+    /// again after a restart.
+    pub seen_jobs: SeenSet,
+    seen_changes: std::sync::Mutex<Vec<SeenChange>>,
     pub shutdown_flag: AtomicBool,
+    pub profile_stats: std::sync::Mutex<HashMap<String, ProfileStats>>,
+    pub health: Health,
+    pub job_history: std::sync::Mutex<HashMap<String, JobRecord>>,
+    pub events: JobEvents,
+    notifications: std::sync::Mutex<VecDeque<NotificationRecord>>,
+    deliveries: std::sync::Mutex<HashMap<String, Delivery>>,
+    /// When each job that no profile lists anymore was last listed.
+    delisted: std::sync::Mutex<HashMap<String, DateTime<Utc>>>,
+    close_after: chrono::Duration,
+    paused: AtomicBool,
 }
 
 impl AppState {
-    pub fn new(initial_jobs: HashSet<String>) -> Self {
+    pub fn new(initial_jobs: SeenSet, history: HashMap<String, JobRecord>, close_after: chrono::Duration) -> Self {
         AppState {
-            seen_jobs: Mutex::new(initial_jobs),
+            seen_jobs: initial_jobs,
+            seen_changes: std::sync::Mutex::new(Vec::new()),
             shutdown_flag: AtomicBool::new(false),
+            profile_stats: std::sync::Mutex::new(HashMap::new()),
+            health: Health::default(),
+            job_history: std::sync::Mutex::new(history),
+            events: JobEvents::new(),
+            notifications: std::sync::Mutex::new(VecDeque::new()),
+            deliveries: std::sync::Mutex::new(HashMap::new()),
+            delisted: std::sync::Mutex::new(HashMap::new()),
+            close_after,
+            paused: AtomicBool::new(false),
+        }
+    }
+
+    /// Makes `profile` show up in the status views before its first poll.
+    pub fn register_profile(&self, profile: &str) {
+        self.profile_stats
+            .lock()
+            .expect("profile stats lock poisoned")
+            .entry(profile.to_string())
+            .or_default();
+    }
+
+    pub fn record_poll_error(&self, profile: &str, error: String) {
+        let mut all_stats = self.profile_stats.lock().expect("profile stats lock poisoned");
+        let stats = all_stats.entry(profile.to_string()).or_default();
+        stats.last_poll = Some(Utc::now());
+        stats.last_error = Some(error);
+    }
+
+    pub fn profile_stats(&self) -> BTreeMap<String, ProfileStats> {
+        let all_stats = self.profile_stats.lock().expect("profile stats lock poisoned");
+        all_stats.iter().map(|(name, stats)| (name.clone(), stats.clone())).collect()
+    }
+
+    /// Pauses or resumes a single profile. Returns `false` for unknown profiles.
+    pub fn set_profile_paused(&self, profile: &str, paused: bool) -> bool {
+        let mut all_stats = self.profile_stats.lock().expect("profile stats lock poisoned");
+        match all_stats.get_mut(profile) {
+            Some(stats) => {
+                stats.paused = paused;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_profile_paused(&self, profile: &str) -> bool {
+        let all_stats = self.profile_stats.lock().expect("profile stats lock poisoned");
+        all_stats.get(profile).is_some_and(|stats| stats.paused)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Adds or refreshes the history entry of every job on a changed result
+    /// page, notes the jobs no profile lists anymore and publishes an event
+    /// for each difference.
+    pub fn record_listed_jobs(&self, profile: &str, jobs: &[JobInfo]) {
+        let now = Utc::now();
+        let mut events = Vec::new();
+        {
+            let mut all_stats = self.profile_stats.lock().expect("profile stats lock poisoned");
+            let listed: HashSet<String> = jobs.iter().map(|job| job.id.clone()).collect();
+            let previous = std::mem::replace(&mut all_stats.entry(profile.to_string()).or_default().listed, listed);
+
+            let mut history = self.job_history.lock().expect("job history lock poisoned");
+            let mut delisted = self.delisted.lock().expect("delisted jobs lock poisoned");
+            for job in jobs {
+                match history.entry(job.id.clone()) {
+                    Entry::Occupied(entry) => {
+                        let record = entry.into_mut();
+                        let changed = record.job != *job || record.closed_at.is_some();
+                        record.job = job.clone();
+                        record.last_seen = now;
+                        record.closed_at = None;
+                        if changed {
+                            events.push((JobEventKind::Updated, record.clone()));
+                        }
+                    }
+                    Entry::Vacant(entry) => {
+                        let record = entry.insert(JobRecord {
+                            job: job.clone(),
+                            profile: profile.to_string(),
+                            first_seen: now,
+                            last_seen: now,
+                            closed_at: None,
+                        });
+                        events.push((JobEventKind::New, record.clone()));
+                    }
+                }
+            }
+
+            for id in previous {
+                if all_stats.values().any(|stats| stats.listed.contains(&id)) {
+                    continue;
+                }
+                if history.get(&id).is_some_and(|record| record.closed_at.is_none()) {
+                    delisted.entry(id).or_insert(now);
+                }
+            }
+            delisted.retain(|id, _| !all_stats.values().any(|stats| stats.listed.contains(id)));
+        }
+
+        for (kind, record) in events {
+            self.events.publish(kind, record);
+        }
+        self.close_delisted_jobs();
+    }
+
+    /// Closes the jobs no profile has listed for `close_after`. Dropping off
+    /// a page alone doesn't mean a job closed, newer jobs may have pushed it
+    /// past `page_size`.
+    pub fn close_delisted_jobs(&self) {
+        let now = Utc::now();
+        let mut events = Vec::new();
+        {
+            let mut delisted = self.delisted.lock().expect("delisted jobs lock poisoned");
+            if delisted.is_empty() {
+                return;
+            }
+            let mut history = self.job_history.lock().expect("job history lock poisoned");
+            delisted.retain(|id, since| {
+                if now - *since < self.close_after {
+                    return true;
+                }
+                if let Some(record) = history.get_mut(id)
+                    && record.closed_at.is_none()
+                {
+                    record.closed_at = Some(now);
+                    events.push((JobEventKind::Closed, record.clone()));
+                }
+                false
+            });
+        }
+
+        // A closed job isn't listed again, so a failed or restored delivery
+        // of it would never be claimed
+        let mut deliveries = self.deliveries.lock().expect("deliveries lock poisoned");
+        for (_, record) in &events {
+            if deliveries.get(&record.job.id).is_some_and(|delivery| delivery.pending.is_empty()) {
+                deliveries.remove(&record.job.id);
+            }
+        }
+        drop(deliveries);
+
+        for (kind, record) in events {
+            self.events.publish(kind, record);
+        }
+    }
+
+    pub fn job_record(&self, job_id: &str) -> Option<JobRecord> {
+        self.job_history.lock().expect("job history lock poisoned").get(job_id).cloned()
+    }
+
+    /// The `count` most recently found jobs, newest first. Only those are
+    /// copied out of the history.
+    pub fn recent_jobs(&self, count: usize) -> Vec<JobRecord> {
+        let history = self.job_history.lock().expect("job history lock poisoned");
+        let mut newest: Vec<&JobRecord> = history.values().collect();
+        if newest.len() > count {
+            newest.select_nth_unstable_by_key(count, |record| Reverse(record.first_seen));
+            newest.truncate(count);
+        }
+        newest.sort_by_key(|record| Reverse(record.first_seen));
+        newest.into_iter().cloned().collect()
+    }
+
+    pub fn job_history(&self) -> Vec<JobRecord> {
+        self.job_history.lock().expect("job history lock poisoned").values().cloned().collect()
+    }
+
+    pub fn record_notification(&self, record: NotificationRecord) {
+        let mut notifications = self.notifications.lock().expect("notification history lock poisoned");
+        if notifications.len() == NOTIFICATION_HISTORY {
+            notifications.pop_front();
+        }
+        notifications.push_back(record);
+    }
+
+    /// Most recent first.
+    pub fn notification_history(&self) -> Vec<NotificationRecord> {
+        let notifications = self.notifications.lock().expect("notification history lock poisoned");
+        notifications.iter().rev().cloned().collect()
+    }
+
+    /// Records a response for `profile` and reports whether it differs from
+    /// the previous one, along with the updated stats.
+    pub fn record_response(&self, profile: &str, hash: u64) -> (bool, ProfileStats) {
+        let mut all_stats = self.profile_stats.lock().expect("profile stats lock poisoned");
+        let stats = all_stats.entry(profile.to_string()).or_default();
+        let changed = stats.last_hash != Some(hash);
+        let now = Utc::now();
+        stats.polls += 1;
+        stats.last_poll = Some(now);
+        stats.last_error = None;
+        if changed {
+            stats.changes += 1;
+            stats.last_change = Some(now);
+            stats.last_hash = Some(hash);
+        }
+        (changed, stats.clone())
+    }
+
+    /// Forgets the last response for `profile` so the next one counts as
+    /// changed, e.g. after it failed to parse.
+    pub fn forget_response(&self, profile: &str) {
+        let mut all_stats = self.profile_stats.lock().expect("profile stats lock poisoned");
+        if let Some(stats) = all_stats.get_mut(profile) {
+            stats.last_hash = None;
         }
     }
 
-    pub async fn add_seen_job(&self, job_id: String) -> bool {
-        let mut jobs = self.seen_jobs.lock().await;
-        jobs.insert(job_id)
+    pub fn add_seen_job(&self, job_id: String) -> bool {
+        let added = self.seen_jobs.insert(job_id.clone());
+        if added {
+            self.push_seen_changes([SeenChange::Added(job_id)]);
+        }
+        added
+    }
+
+    pub fn remove_seen_job(&self, job_id: &str) -> bool {
+        let removed = self.seen_jobs.remove(job_id);
+        if removed {
+            self.push_seen_changes([SeenChange::Removed(job_id.to_string())]);
+        }
+        removed
     }
 
-    pub async fn get_seen_jobs(&self) -> HashSet<String> {
-        self.seen_jobs.lock().await.clone()
+    fn push_seen_changes(&self, changes: impl IntoIterator<Item = SeenChange>) {
+        self.seen_changes.lock().expect("seen changes lock poisoned").extend(changes);
+    }
+
+    /// Seen-set changes since the last call, oldest first.
+    pub fn take_seen_changes(&self) -> Vec<SeenChange> {
+        std::mem::take(&mut *self.seen_changes.lock().expect("seen changes lock poisoned"))
+    }
+
+    /// Claims the jobs from one result set that nobody has notified or is
+    /// notifying yet. Jobs only move between claimed and seen under the
+    /// deliveries lock, so overlapping polls can't split or repeat a set.
+    pub fn claim_new_jobs(&self, profile: &str, jobs: &[JobInfo]) -> Vec<JobInfo> {
+        let now = Utc::now();
+        let mut deliveries = self.deliveries.lock().expect("deliveries lock poisoned");
+        let mut claimed = Vec::new();
+        let mut waiting = false;
+        for job in jobs {
+            if self.seen_jobs.contains(&job.id) {
+                continue;
+            }
+            let delivery = deliveries.entry(job.id.clone()).or_default();
+            if delivery.state != NotificationState::Failed || !delivery.pending.is_empty() {
+                continue;
+            }
+            if delivery.retry_at.is_some_and(|at| at > now) {
+                waiting = true;
+                continue;
+            }
+            delivery.state = NotificationState::Pending;
+            claimed.push(job.clone());
+        }
+        drop(deliveries);
+
+        // An unchanged page would be skipped before the retry falls due
+        if waiting {
+            self.forget_response(profile);
+        }
+        claimed
+    }
+
+    /// Records which of the subscribers whose filter accepts a claimed job it
+    /// goes out to, leaving out the ones an earlier attempt reached, and
+    /// returns those. A job with nobody left to send to is done right away.
+    pub fn expect_deliveries<'s>(&self, job_id: &str, subscribers: Vec<&'s str>) -> Vec<&'s str> {
+        let mut deliveries = self.deliveries.lock().expect("deliveries lock poisoned");
+        let Some(delivery) = deliveries.get_mut(job_id) else {
+            return Vec::new();
+        };
+        let recipients: Vec<&str> = subscribers
+            .into_iter()
+            .filter(|subscriber| !delivery.done.contains(*subscriber))
+            .collect();
+        delivery.pending.extend(recipients.iter().map(|subscriber| subscriber.to_string()));
+        drop(deliveries);
+
+        if recipients.is_empty() {
+            self.settle_jobs(&[job_id.to_string()], |_, _| None);
+        }
+        recipients
+    }
+
+    /// Settles the subscriber's message carrying each of the jobs. A failed
+    /// one releases the job for a later poll, after a growing delay, until
+    /// the subscriber runs out of attempts. A job moves to the seen set once
+    /// no subscriber is left to get it. Returns how many became seen.
+    pub fn record_delivery(&self, job_ids: &[String], subscriber: &str, outcome: DeliveryOutcome) -> usize {
+        let now = Utc::now();
+        self.settle_jobs(job_ids, |job_id, delivery| {
+            let subscriber = delivery.pending.take(subscriber)?;
+            if outcome == DeliveryOutcome::Failed
+                && let Some(delay) = delivery.record_failure(job_id, &subscriber)
+            {
+                delivery.state = NotificationState::Failed;
+                delivery.retry_at = delivery.retry_at.max(Some(now + delay));
+                return None;
+            }
+            delivery.done.insert(subscriber.clone());
+            Some(subscriber)
+        })
+    }
+
+    /// Counts a failed digest or release of held jobs, which the notifier
+    /// retries itself, so the subscriber stays pending for the jobs worth
+    /// another attempt. The rest are given up on for them. Returns the jobs
+    /// to retry and how many became seen.
+    pub fn retry_later(&self, job_ids: &[String], subscriber: &str) -> (Vec<String>, usize) {
+        let mut retry = Vec::new();
+        let sent = self.settle_jobs(job_ids, |job_id, delivery| {
+            if !delivery.pending.contains(subscriber) {
+                return None;
+            }
+            if delivery.record_failure(job_id, subscriber).is_some() {
+                retry.push(job_id.to_string());
+                return None;
+            }
+            let subscriber = delivery.pending.take(subscriber)?;
+            delivery.done.insert(subscriber.clone());
+            Some(subscriber)
+        });
+        (retry, sent)
+    }
+
+    /// Applies `update` to each job's delivery, then finishes the ones with
+    /// no message left to wait for. `update` returns the subscriber it
+    /// marked done, if any, which is journaled while others still wait.
+    fn settle_jobs(&self, job_ids: &[String], mut update: impl FnMut(&str, &mut Delivery) -> Option<String>) -> usize {
+        let mut deliveries = self.deliveries.lock().expect("deliveries lock poisoned");
+        let mut done = Vec::new();
+        let mut changes = Vec::new();
+        let mut released = false;
+        for job_id in job_ids {
+            let Some(delivery) = deliveries.get_mut(job_id) else {
+                continue;
+            };
+            let subscriber = update(job_id, delivery);
+            if !delivery.pending.is_empty() || delivery.state == NotificationState::Failed {
+                changes.extend(subscriber.map(|subscriber| SeenChange::Delivered { job_id: job_id.clone(), subscriber }));
+                released |= delivery.pending.is_empty();
+                continue;
+            }
+            deliveries.remove(job_id);
+            done.push(job_id.clone());
+        }
+        let sent = self.seen_jobs.insert_many(done.iter().cloned());
+        changes.extend(done.into_iter().map(SeenChange::Added));
+        self.push_seen_changes(changes);
+        drop(deliveries);
+
+        if released {
+            self.retry_unchanged_pages();
+        }
+        sent
+    }
+
+    /// Hands claimed jobs that never made it to the notifier back to the
+    /// next poll.
+    pub fn release_jobs(&self, job_ids: &[String]) {
+        let mut deliveries = self.deliveries.lock().expect("deliveries lock poisoned");
+        for job_id in job_ids {
+            if let Some(delivery) = deliveries.get_mut(job_id) {
+                delivery.state = NotificationState::Failed;
+                delivery.retry_at = None;
+            }
+        }
+        drop(deliveries);
+        self.retry_unchanged_pages();
+    }
+
+    /// Gives up on every message still queued or in flight, for when the
+    /// notifier restarted and lost its queues. The jobs go out again, to the
+    /// subscribers that missed them, with the next poll that lists them.
+    /// Returns how many were released.
+    pub fn release_pending_deliveries(&self) -> usize {
+        let mut deliveries = self.deliveries.lock().expect("deliveries lock poisoned");
+        let mut released = 0;
+        for delivery in deliveries.values_mut().filter(|delivery| !delivery.pending.is_empty()) {
+            delivery.pending.clear();
+            delivery.state = NotificationState::Failed;
+            delivery.retry_at = None;
+            released += 1;
+        }
+        drop(deliveries);
+        if released > 0 {
+            self.retry_unchanged_pages();
+        }
+        released
+    }
+
+    /// Takes back which subscribers each unfinished job reached before a
+    /// restart, so the next claim leaves them out.
+    pub fn restore_deliveries(&self, done: HashMap<String, HashSet<String>>) {
+        let mut deliveries = self.deliveries.lock().expect("deliveries lock poisoned");
+        for (job_id, subscribers) in done {
+            if !self.seen_jobs.contains(&job_id) {
+                deliveries.entry(job_id).or_default().done.extend(subscribers);
+            }
+        }
+    }
+
+    /// The subscribers each unfinished job reached, as journal entries that
+    /// outlive a compaction.
+    pub fn unfinished_deliveries(&self) -> Vec<SeenChange> {
+        let deliveries = self.deliveries.lock().expect("deliveries lock poisoned");
+        deliveries
+            .iter()
+            .flat_map(|(job_id, delivery)| {
+                delivery.done.iter().map(|subscriber| SeenChange::Delivered {
+                    job_id: job_id.clone(),
+                    subscriber: subscriber.clone(),
+                })
+            })
+            .collect()
+    }
+
+    fn retry_unchanged_pages(&self) {
+        // An unchanged page is otherwise skipped, so the jobs would only
+        // come back once something else about the listing changed
+        let mut all_stats = self.profile_stats.lock().expect("profile stats lock poisoned");
+        for stats in all_stats.values_mut() {
+            stats.last_hash = None;
+        }
+    }
+
+    pub fn notification_state(&self, job_id: &str) -> Option<NotificationState> {
+        if self.seen_jobs.contains(job_id) {
+            return Some(NotificationState::Sent);
+        }
+        let deliveries = self.deliveries.lock().expect("deliveries lock poisoned");
+        deliveries.get(job_id).map(|delivery| delivery.state)
     }
 }
 
 pub struct NotificationBatch {
     pub location: String,
     pub jobs: Vec<JobInfo>,
-}
\ No newline at end of file
+}
+
+pub enum Notification {
+    Jobs(NotificationBatch),
+    /// High-priority operator message, e.g. broken credentials.
+    Alert(String),
+    /// Plain-text analytics report.
+    Report(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: &str) -> JobInfo {
+        JobInfo {
+            id: id.into(),
+            title: "Warehouse Associate".into(),
+            location: "Brampton, ON".into(),
+            job_type: "Full Time".into(),
+            pay_min: Some(19.5),
+            pay_max: Some(21.0),
+            shift: Some(4),
+        }
+    }
+
+    fn state() -> AppState {
+        AppState::new(SeenSet::new(), HashMap::new(), chrono::Duration::hours(6))
+    }
+
+    #[test]
+    fn a_retry_only_goes_to_the_subscribers_that_missed_the_job() {
+        let state = state();
+        let ids = ["J1".to_string()];
+        assert_eq!(state.claim_new_jobs("default", &[job("J1")]).len(), 1);
+        assert_eq!(state.expect_deliveries("J1", vec!["a", "b"]), ["a", "b"]);
+        state.record_delivery(&ids, "a", DeliveryOutcome::Delivered);
+        assert_eq!(state.notification_state("J1"), Some(NotificationState::Pending));
+        state.record_delivery(&ids, "b", DeliveryOutcome::Failed);
+        assert_eq!(state.notification_state("J1"), Some(NotificationState::Failed));
+
+        assert_eq!(state.claim_new_jobs("default", &[job("J1")]).len(), 1);
+        assert_eq!(state.expect_deliveries("J1", vec!["a", "b"]), ["b"]);
+        assert_eq!(state.record_delivery(&ids, "b", DeliveryOutcome::Delivered), 1);
+        assert_eq!(state.notification_state("J1"), Some(NotificationState::Sent));
+        assert!(state.claim_new_jobs("default", &[job("J1")]).is_empty());
+    }
+
+    #[test]
+    fn a_pending_job_is_not_claimed_again() {
+        let state = state();
+        state.claim_new_jobs("default", &[job("J1")]);
+        state.expect_deliveries("J1", vec!["a"]);
+        assert!(state.claim_new_jobs("default", &[job("J1")]).is_empty());
+
+        // Expecting the same subscriber twice still takes one delivery
+        state.expect_deliveries("J1", vec!["a"]);
+        assert_eq!(state.record_delivery(&["J1".to_string()], "a", DeliveryOutcome::Delivered), 1);
+    }
+
+    #[test]
+    fn a_restarted_notifier_releases_what_was_queued() {
+        let state = state();
+        state.claim_new_jobs("default", &[job("J1"), job("J2")]);
+        state.expect_deliveries("J1", vec!["a"]);
+        assert_eq!(state.release_pending_deliveries(), 1);
+        assert_eq!(state.notification_state("J1"), Some(NotificationState::Failed));
+        // Still on its way to the notifier
+        assert_eq!(state.notification_state("J2"), Some(NotificationState::Pending));
+        assert_eq!(state.claim_new_jobs("default", &[job("J1"), job("J2")]), [job("J1")]);
+    }
+
+    #[test]
+    fn a_refusing_chat_is_not_retried() {
+        let state = state();
+        let ids = ["J1".to_string()];
+        state.claim_new_jobs("default", &[job("J1")]);
+        state.expect_deliveries("J1", vec!["a", "b"]);
+        assert_eq!(state.record_delivery(&ids, "a", DeliveryOutcome::Rejected), 0);
+        assert_eq!(state.record_delivery(&ids, "b", DeliveryOutcome::Delivered), 1);
+        assert_eq!(state.notification_state("J1"), Some(NotificationState::Sent));
+    }
+
+    #[test]
+    fn failed_messages_back_off_and_give_up() {
+        let state = state();
+        let ids = ["J1".to_string()];
+        state.register_profile("default");
+        state.claim_new_jobs("default", &[job("J1")]);
+        state.expect_deliveries("J1", vec!["a"]);
+        state.record_delivery(&ids, "a", DeliveryOutcome::Failed);
+        // The first retry goes out with the next poll
+        assert_eq!(state.claim_new_jobs("default", &[job("J1")]).len(), 1);
+        state.expect_deliveries("J1", vec!["a"]);
+        state.record_delivery(&ids, "a", DeliveryOutcome::Failed);
+        state.record_response("default", 1);
+        assert!(state.claim_new_jobs("default", &[job("J1")]).is_empty());
+        // The page is read again until the retry falls due
+        assert!(state.record_response("default", 1).0);
+
+        // Digests and held jobs are retried by the notifier, up to the cap
+        let state = self::state();
+        state.claim_new_jobs("default", &[job("J1")]);
+        state.expect_deliveries("J1", vec!["a"]);
+        for _ in 1..MAX_DELIVERY_ATTEMPTS {
+            assert_eq!(state.retry_later(&ids, "a"), (ids.to_vec(), 0));
+        }
+        assert_eq!(state.retry_later(&ids, "a"), (Vec::new(), 1));
+        assert!(state.seen_jobs.contains("J1"));
+    }
+
+    #[test]
+    fn retry_delays_grow_to_half_an_hour() {
+        let delays: Vec<i64> = (1..=9).map(|failures| retry_delay(failures).num_seconds()).collect();
+        assert_eq!(delays, [0, 30, 60, 120, 240, 480, 960, 1800, 1800]);
+    }
+
+    #[test]
+    fn deliveries_to_some_subscribers_survive_a_restart() {
+        let state = state();
+        let ids = ["J1".to_string()];
+        state.claim_new_jobs("default", &[job("J1")]);
+        state.expect_deliveries("J1", vec!["instant", "digest"]);
+        state.record_delivery(&ids, "instant", DeliveryOutcome::Delivered);
+        let journaled = [SeenChange::Delivered { job_id: "J1".into(), subscriber: "instant".into() }];
+        assert_eq!(state.take_seen_changes(), journaled);
+        assert_eq!(state.unfinished_deliveries(), journaled);
+
+        let restarted = self::state();
+        restarted.restore_deliveries(HashMap::from([("J1".to_string(), HashSet::from(["instant".to_string()]))]));
+        assert_eq!(restarted.claim_new_jobs("default", &[job("J1")]).len(), 1);
+        assert_eq!(restarted.expect_deliveries("J1", vec!["instant", "digest"]), ["digest"]);
+    }
+
+    #[test]
+    fn a_job_nobody_wants_is_done_at_once() {
+        let state = state();
+        state.claim_new_jobs("default", &[job("J1")]);
+        assert!(state.expect_deliveries("J1", Vec::new()).is_empty());
+        assert!(state.seen_jobs.contains("J1"));
+        assert_eq!(state.take_seen_changes(), [SeenChange::Added("J1".into())]);
+    }
+}
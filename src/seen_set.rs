@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+use std::hash::{BuildHasher, RandomState};
+use std::sync::RwLock;
+
+/// Enough that polls running side by side rarely wait on the same lock.
+const SHARDS: usize = 64;
+
+/// A set of job IDs split over independently locked shards, so concurrent
+/// lookups and inserts only contend when they land on the same shard.
+pub struct SeenSet {
+    hasher: RandomState,
+    shards: Box<[RwLock<HashSet<String>>]>,
+}
+
+impl SeenSet {
+    pub fn new() -> Self {
+        SeenSet {
+            hasher: RandomState::new(),
+            shards: (0..SHARDS).map(|_| RwLock::new(HashSet::new())).collect(),
+        }
+    }
+
+    fn shard_index(&self, id: &str) -> usize {
+        self.hasher.hash_one(id) as usize % SHARDS
+    }
+
+    fn shard(&self, id: &str) -> &RwLock<HashSet<String>> {
+        &self.shards[self.shard_index(id)]
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.shard(id).read().expect("seen shard poisoned").contains(id)
+    }
+
+    pub fn insert(&self, id: String) -> bool {
+        self.shard(&id).write().expect("seen shard poisoned").insert(id)
+    }
+
+    /// Inserts the IDs taking each shard's lock once. Returns how many were new.
+    pub fn insert_many(&self, ids: impl IntoIterator<Item = String>) -> usize {
+        let mut by_shard: Vec<Vec<String>> = vec![Vec::new(); SHARDS];
+        for id in ids {
+            by_shard[self.shard_index(&id)].push(id);
+        }
+
+        let mut added = 0;
+        for (shard, ids) in self.shards.iter().zip(by_shard) {
+            if ids.is_empty() {
+                continue;
+            }
+            let mut shard = shard.write().expect("seen shard poisoned");
+            for id in ids {
+                added += shard.insert(id) as usize;
+            }
+        }
+        added
+    }
+
+    pub fn remove(&self, id: &str) -> bool {
+        self.shard(id).write().expect("seen shard poisoned").remove(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.read().expect("seen shard poisoned").len()).sum()
+    }
+
+    /// Visits every ID a shard at a time, copying one shard out and letting
+    /// go of its lock before visiting, so slow visitors like file writes
+    /// don't hold up inserts. IDs inserted during the walk may or may not be
+    /// visited.
+    pub fn for_each(&self, mut visit: impl FnMut(&str)) {
+        let mut buffer = Vec::new();
+        for shard in self.shards.iter() {
+            buffer.extend(shard.read().expect("seen shard poisoned").iter().cloned());
+            for id in buffer.drain(..) {
+                visit(&id);
+            }
+        }
+    }
+}
+
+impl FromIterator<String> for SeenSet {
+    fn from_iter<I: IntoIterator<Item = String>>(ids: I) -> Self {
+        let set = SeenSet::new();
+        set.insert_many(ids);
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn insert_many_counts_only_new_ids() {
+        let set = SeenSet::new();
+        assert!(set.insert("J1".into()));
+        assert_eq!(set.insert_many(ids(&["J1", "J2", "J3", "J2"])), 2);
+        assert_eq!(set.insert_many(ids(&["J1", "J2", "J3"])), 0);
+        assert_eq!(set.insert_many(Vec::new()), 0);
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn remove_reports_whether_the_id_was_there() {
+        let set: SeenSet = ids(&["J1", "J2"]).into_iter().collect();
+        assert!(set.remove("J1"));
+        assert!(!set.remove("J1"));
+        assert!(!set.remove("J9"));
+        assert!(!set.contains("J1"));
+        assert!(set.contains("J2"));
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn for_each_visits_every_id_once() {
+        let expected: Vec<String> = (0..500).map(|i| format!("J{}", i)).collect();
+        let set: SeenSet = expected.iter().cloned().collect();
+        let mut visited = Vec::new();
+        set.for_each(|id| visited.push(id.to_string()));
+        visited.sort();
+        let mut expected = expected;
+        expected.sort();
+        assert_eq!(visited, expected);
+    }
+}
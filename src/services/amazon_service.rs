@@ -1,80 +1,310 @@
-use anyhow::Result;
-use crate::config::Config;
-use crate::model::{AppState, JobInfo, ApiResponse};
+use async_channel::Sender;
+use crate::config::{Config, SearchProfile, Secret};
+use crate::metrics::{
+    FETCHES, FETCH_DURATION, FETCH_RETRIES, JOBS_LISTED, LAST_SUCCESSFUL_FETCH, RESPONSES,
+};
+use crate::model::{AppState, JobCard, JobInfo, ApiResponse, Notification};
+use crate::services::proxy_service::ProxyPool;
+use crate::services::schema_service::SchemaService;
+use crate::services::token_service::TokenService;
 use crate::utils::backoff_strategy;
 use chrono::Utc;
-use log::{warn};
+use log::{debug, info, warn};
 use rand::random;
-use reqwest::Client;
-use serde_json::json;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+use tracing::{info_span, Instrument};
 
-const USER_AGENTS: &[&str] = &[
-    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36",
-    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/14.1.1 Safari/605.1.15",
-    "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/92.0.4515.107 Safari/537.36",
-    "Mozilla/5.0 (iPhone; CPU iPhone OS 14_6 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/14.1.1 Mobile/15E148 Safari/604.1",
-];
+/// Attempts allowed for 5xx before handing over to the scheduler's backoff.
+const SERVER_ERROR_ATTEMPTS: usize = 2;
+
+/// Why a fetch failed. Every variant carries already-redacted text.
+#[derive(Debug)]
+pub enum FetchError {
+    Network(String),
+    Timeout,
+    /// 401/403, or no token could be obtained.
+    Auth(String),
+    /// 429, with the server's Retry-After if it sent one.
+    Throttled { retry_after: Option<Duration>, body: String },
+    Server { status: StatusCode, body: String },
+    /// Any other non-success status.
+    Http { status: StatusCode, body: String },
+    /// HTTP 200 carrying a GraphQL `errors` array and no data.
+    GraphQl(Vec<String>),
+    /// The body is not the shape we expect.
+    Schema(String),
+}
+
+enum RetryPolicy {
+    /// Retry with exponential backoff, up to this many attempts in total.
+    Backoff(usize),
+    /// Refresh the token and try once more.
+    RefreshToken,
+    /// Retrying right away won't help; hand the error to the caller.
+    GiveUp,
+}
+
+impl FetchError {
+    /// Short label used in logs and metrics.
+    pub fn class(&self) -> &'static str {
+        match self {
+            FetchError::Network(_) => "network",
+            FetchError::Timeout => "timeout",
+            FetchError::Auth(_) => "auth",
+            FetchError::Throttled { .. } => "throttled",
+            FetchError::Server { .. } => "server",
+            FetchError::Http { .. } => "http",
+            FetchError::GraphQl(_) => "graphql",
+            FetchError::Schema(_) => "schema",
+        }
+    }
+
+    /// Whether the scheduler should slow everything down.
+    pub fn is_throttling(&self) -> bool {
+        matches!(self, FetchError::Throttled { .. } | FetchError::Server { .. })
+    }
+
+    /// Whether the egress proxy is the likely culprit, as opposed to Amazon
+    /// or our request.
+    pub fn is_proxy_fault(&self) -> bool {
+        match self {
+            FetchError::Network(_) | FetchError::Timeout | FetchError::Throttled { .. } => true,
+            FetchError::Http { status, .. } => *status == StatusCode::PROXY_AUTHENTICATION_REQUIRED,
+            _ => false,
+        }
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            FetchError::Throttled { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    fn retry_policy(&self, max_retries: usize) -> RetryPolicy {
+        match self {
+            FetchError::Network(_) | FetchError::Timeout => RetryPolicy::Backoff(max_retries),
+            FetchError::Server { .. } => RetryPolicy::Backoff(SERVER_ERROR_ATTEMPTS.min(max_retries)),
+            FetchError::Auth(_) => RetryPolicy::RefreshToken,
+            // Throttling is handled globally by the scheduler; GraphQL, schema
+            // and other client errors will just fail the same way again
+            FetchError::Throttled { .. }
+            | FetchError::Http { .. }
+            | FetchError::GraphQl(_)
+            | FetchError::Schema(_) => RetryPolicy::GiveUp,
+        }
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] ", self.class())?;
+        match self {
+            FetchError::Network(e) => write!(f, "{}", e),
+            FetchError::Timeout => write!(f, "request timed out"),
+            FetchError::Auth(e) => write!(f, "{}", e),
+            FetchError::Throttled { body, .. } => write!(f, "HTTP 429: {}", body),
+            FetchError::Server { status, body } | FetchError::Http { status, body } => {
+                write!(f, "HTTP error {}: {}", status, body)
+            }
+            FetchError::GraphQl(messages) => write!(f, "GraphQL errors: {}", messages.join("; ")),
+            FetchError::Schema(e) => write!(f, "unexpected response: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
 
 pub struct AmazonService {
-    client: Client,
+    proxies: ProxyPool,
     config: Config,
+    token_service: TokenService,
+    schema_service: SchemaService,
 }
 
 impl AmazonService {
-    pub fn new(config: Config) -> Self {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .build()
+    pub fn new(config: Config, alerts: Sender<Notification>) -> Self {
+        let proxies = ProxyPool::new(&config.amazon.proxy, &config.http)
             .expect("Failed to create HTTP client");
-            
-        AmazonService { client, config }
+        let schema_service = SchemaService::new(&config.schema.capture_dir, alerts.clone());
+        let token_service = TokenService::new(proxies.primary_client(), config.clone(), alerts);
+
+        AmazonService { proxies, config, token_service, schema_service }
     }
 
+    /// Fetches the result page for `profile`. Returns `None` when the page is
+    /// byte-for-byte the same as the previous one, so callers can skip the
+    /// dedupe pass entirely.
     pub async fn fetch_jobs(
         &self,
         state: &AppState,
-    ) -> Result<Vec<JobInfo>> {
-        for attempt in 0..self.config.rate_limiting.max_retries {
+        profile: &SearchProfile,
+    ) -> Result<Option<Vec<JobInfo>>, FetchError> {
+        let mut attempt = 0;
+        let mut refreshed_after_rejection = false;
+        loop {
             if state.shutdown_flag.load(std::sync::atomic::Ordering::Relaxed) {
-                return Ok(Vec::new());
+                return Ok(None);
             }
 
-            match self.try_fetch_jobs().await {
-                Ok(jobs) => return Ok(jobs),
+            // Token failures are escalated by the token service; retrying
+            // here would only hammer the token endpoint
+            let token = match self.token_service.token().await {
+                Ok(token) => token,
                 Err(e) => {
+                    FETCHES.with_label_values(&[profile.name.as_str(), "auth"]).inc();
+                    return Err(FetchError::Auth(format!("no API token available: {:#}", e)));
+                }
+            };
+
+            // Every attempt takes its own proxy, so a retry goes out elsewhere
+            let proxy = self.proxies.pick();
+            let timer = FETCH_DURATION.with_label_values(&[profile.name.as_str()]).start_timer();
+            let result = self
+                .try_fetch_jobs(&proxy.client, &token, profile)
+                .instrument(info_span!("fetch", attempt = attempt + 1, proxy = %proxy.label))
+                .await;
+            timer.observe_duration();
+            self.proxies.report(&proxy, result.as_ref().err().is_none_or(|e| !e.is_proxy_fault()));
+
+            let error = match result {
+                Ok((body, page)) => {
+                    FETCHES.with_label_values(&[profile.name.as_str(), "success"]).inc();
+                    self.token_service.report_success().await;
+                    // An unchanged page parsed fine the last time
+                    if !Self::page_changed(state, profile, &body) {
+                        Self::record_successful_fetch(state);
+                        return Ok(None);
+                    }
+                    self.schema_service.observe(&profile.name, &body, &page).await;
+                    let jobs = Self::parse_jobs(profile, page).inspect_err(|_| {
+                        // Otherwise the same broken page would pass as unchanged
+                        state.forget_response(&profile.name);
+                    })?;
+                    Self::record_successful_fetch(state);
+                    JOBS_LISTED.with_label_values(&[profile.name.as_str()]).set(jobs.len() as i64);
+                    return Ok(Some(jobs));
+                }
+                Err(e) => e,
+            };
+            FETCHES.with_label_values(&[profile.name.as_str(), error.class()]).inc();
+
+            match error.retry_policy(self.config.rate_limiting.max_retries) {
+                RetryPolicy::RefreshToken
+                    if !refreshed_after_rejection && self.token_service.invalidate().await =>
+                {
+                    refreshed_after_rejection = true;
+                    FETCH_RETRIES.with_label_values(&[error.class()]).inc();
+                    warn!("Amazon API rejected the token ({}). Refreshing it", error);
+                }
+                RetryPolicy::RefreshToken => {
+                    self.token_service.report_rejected(&error.to_string()).await;
+                    return Err(error);
+                }
+                RetryPolicy::Backoff(max_attempts) if attempt + 1 < max_attempts => {
                     let delay = backoff_strategy(
                         attempt as u32,
                         self.config.rate_limiting.retry_base_ms,
                         self.config.rate_limiting.retry_max_delay_ms,
                     );
-                    
+                    FETCH_RETRIES.with_label_values(&[error.class()]).inc();
+
                     warn!(
                         "Attempt {}/{} failed: {}. Retrying in {:?}",
                         attempt + 1,
-                        self.config.rate_limiting.max_retries,
-                        e,
+                        max_attempts,
+                        error,
                         delay
                     );
-                    
+
                     tokio::time::sleep(delay).await;
+                    attempt += 1;
                 }
+                RetryPolicy::Backoff(_) | RetryPolicy::GiveUp => return Err(error),
             }
         }
-        
-        Err(anyhow::anyhow!("All retry attempts exhausted"))
     }
 
-    async fn try_fetch_jobs(&self) -> Result<Vec<JobInfo>> {
+    fn record_successful_fetch(state: &AppState) {
+        LAST_SUCCESSFUL_FETCH.set(Utc::now().timestamp() as f64);
+        state.health.record_successful_fetch();
+    }
+
+    fn page_changed(state: &AppState, profile: &SearchProfile, body: &str) -> bool {
+        let mut hasher = DefaultHasher::new();
+        body.hash(&mut hasher);
+        let (changed, stats) = state.record_response(&profile.name, hasher.finish());
+
+        let result = if changed { "changed" } else { "unchanged" };
+        RESPONSES.with_label_values(&[profile.name.as_str(), result]).inc();
+        if !changed {
+            debug!("Profile {}: response unchanged, skipping", profile.name);
+        }
+        if stats.polls % 100 == 0 {
+            info!(
+                "Profile {}: {} of {} responses changed ({:.1}%)",
+                profile.name,
+                stats.changes,
+                stats.polls,
+                100.0 * stats.changes as f64 / stats.polls as f64
+            );
+        }
+        changed
+    }
+
+    fn parse_jobs(profile: &SearchProfile, page: Value) -> Result<Vec<JobInfo>, FetchError> {
+        let response_json: ApiResponse = serde_json::from_value(page)
+            .map_err(|e| FetchError::Schema(e.to_string()))?;
+        let jobs = response_json.data.search_job_cards.job_cards
+            .into_iter()
+            .filter_map(|raw| {
+                let card: JobCard = match serde_json::from_value(raw.clone()) {
+                    Ok(card) => card,
+                    Err(e) => {
+                        warn!(
+                            "Profile {}: skipping malformed job card ({}): {}",
+                            profile.name, e, raw
+                        );
+                        return None;
+                    }
+                };
+                Some(JobInfo {
+                    id: card.id,
+                    title: card.title.unwrap_or_else(|| "Untitled job".into()),
+                    location: card.location.unwrap_or_else(|| "Unknown location".into()),
+                    job_type: card.job_type.unwrap_or_default(),
+                    pay_min: card.pay_min,
+                    pay_max: card.pay_max,
+                    shift: card.shift,
+                })
+            })
+            .collect();
+
+        Ok(jobs)
+    }
+
+    /// Performs one request and classifies whatever goes wrong. Returns the
+    /// raw body alongside its parsed JSON.
+    async fn try_fetch_jobs(
+        &self,
+        client: &Client,
+        token: &Secret,
+        profile: &SearchProfile,
+    ) -> Result<(String, Value), FetchError> {
         let today = Utc::now().format("%Y-%m-%d").to_string();
-        let user_agent = USER_AGENTS[random::<usize>() % USER_AGENTS.len()];
 
         let payload = json!({
             "operationName": "searchJobCardsByLocation",
             "variables": {
                 "searchJobRequest": {
-                    "locale": &self.config.amazon.locale,
-                    "country": &self.config.amazon.country,
-                    "keyWords": "",
+                    "locale": &profile.locale,
+                    "country": &profile.country,
+                    "keyWords": &profile.keywords,
                     "equalFilters": [],
                     "dateFilters": [
                         {
@@ -91,35 +321,73 @@ impl AmazonService {
             "query": "query searchJobCardsByLocation($searchJobRequest: SearchJobRequest!) {\n  searchJobCardsByLocation(searchJobRequest: $searchJobRequest) {\n    nextToken\n    jobCards {\n      jobId\n      jobTitle\n      jobType\n      locationName\n    scheduleCount\n      totalPayRateMin\n      totalPayRateMax\n    }\n  }\n}"
         });
 
-        let response = self.client
-            .post(&self.config.amazon.api_url)
-            .header("User-Agent", user_agent)
-            .header("Authorization", format!("Bearer {}", self.config.amazon.api_token))
-            .header("Country", &self.config.amazon.country)
+        let mut request = client.post(&self.config.amazon.api_url);
+        let header_profiles = &self.config.http.header_profiles;
+        if !header_profiles.is_empty() {
+            let headers = &header_profiles[random::<usize>() % header_profiles.len()];
+            request = request.header("User-Agent", &headers.user_agent);
+            for (name, value) in &headers.headers {
+                request = request.header(name, value);
+            }
+        }
+        let response = request
+            .header("Authorization", format!("Bearer {}", token.expose()))
+            .header("Country", &profile.country)
             .json(&payload)
             .send()
-            .await?;
+            .await
+            .map_err(|e| self.request_error(e))?;
+
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse().ok())
+            .map(Duration::from_secs);
+        let body = response.text().await.map_err(|e| self.request_error(e))?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await?;
-            return Err(anyhow::anyhow!("HTTP error {}: {}", status, body));
+        if !status.is_success() {
+            let body = self.config.redact(&body);
+            return Err(match status {
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                    FetchError::Auth(format!("HTTP error {}: {}", status, body))
+                }
+                StatusCode::TOO_MANY_REQUESTS => FetchError::Throttled { retry_after, body },
+                status if status.is_server_error() => FetchError::Server { status, body },
+                status => FetchError::Http { status, body },
+            });
         }
 
-        let response_json: ApiResponse = response.json().await?;
-        let jobs = response_json.data.search_job_cards.job_cards
-            .into_iter()
-            .map(|card| JobInfo {
-                id: card.id,
-                title: card.title,
-                location: card.location,
-                job_type: card.job_type,
-                pay_min: card.pay_min,
-                pay_max: card.pay_max,
-                shift: card.shift,
-            })
-            .collect();
+        let page: Value = serde_json::from_str(&body)
+            .map_err(|e| FetchError::Schema(format!("invalid JSON: {}", e)))?;
 
-        Ok(jobs)
+        // GraphQL reports failures with HTTP 200 and an `errors` array
+        if let Some(errors) = page.get("errors").and_then(Value::as_array).filter(|e| !e.is_empty()) {
+            let messages: Vec<String> = errors
+                .iter()
+                .map(|e| {
+                    e.get("message")
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                        .unwrap_or_else(|| e.to_string())
+                })
+                .map(|message| self.config.redact(&message))
+                .collect();
+            if page.get("data").is_none_or(Value::is_null) {
+                return Err(FetchError::GraphQl(messages));
+            }
+            warn!("Partial GraphQL response for profile {}: {}", profile.name, messages.join("; "));
+        }
+
+        Ok((body, page))
+    }
+
+    fn request_error(&self, e: reqwest::Error) -> FetchError {
+        if e.is_timeout() {
+            FetchError::Timeout
+        } else {
+            FetchError::Network(self.config.redact(&format!("{:#}", e)))
+        }
     }
 }
\ No newline at end of file
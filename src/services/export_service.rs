@@ -0,0 +1,368 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use crate::config::{ExportConfig, ExportFormat};
+use crate::model::{AppState, ExportColumn, JobEventKind, JobFilter, JobRecord};
+use log::{info, warn};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+
+/// File names for one-off exports and for the continuous sink.
+const EXPORT_STEM: &str = "jobs";
+const SINK_STEM: &str = "new_jobs";
+
+/// What to write and where.
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub dir: PathBuf,
+    pub partition_by_day: bool,
+    pub columns: Vec<ExportColumn>,
+    pub filter: JobFilter,
+}
+
+impl ExportOptions {
+    pub fn from_config(config: &ExportConfig) -> Result<Self> {
+        Ok(ExportOptions {
+            format: config.format,
+            dir: PathBuf::from(&config.dir),
+            partition_by_day: config.partition_by_day,
+            columns: ExportColumn::parse_list(&config.columns).map_err(anyhow::Error::msg)?,
+            filter: config.filter.clone(),
+        })
+    }
+
+    /// `<stem>.<ext>`, inside `date=YYYY-MM-DD/` when partitioning.
+    fn path_for(&self, stem: &str, day: Option<NaiveDate>) -> PathBuf {
+        let file_name = format!("{}.{}", stem, self.format.extension());
+        match day {
+            Some(day) => self.dir.join(format!("date={}", day.format("%Y-%m-%d"))).join(file_name),
+            None => self.dir.join(file_name),
+        }
+    }
+}
+
+/// A single value in an export row.
+enum Cell {
+    Text(Option<String>),
+    Float(Option<f64>),
+    Int(Option<i64>),
+    Time(Option<DateTime<Utc>>),
+}
+
+fn cell(column: ExportColumn, record: &JobRecord) -> Cell {
+    let job = &record.job;
+    match column {
+        ExportColumn::Id => Cell::Text(Some(job.id.clone())),
+        ExportColumn::Title => Cell::Text(Some(job.title.clone())),
+        ExportColumn::Location => Cell::Text(Some(job.location.clone())),
+        ExportColumn::JobType => Cell::Text(Some(job.job_type.clone())),
+        ExportColumn::PayMin => Cell::Float(job.pay_min),
+        ExportColumn::PayMax => Cell::Float(job.pay_max),
+        ExportColumn::Shift => Cell::Int(job.shift),
+        ExportColumn::Profile => Cell::Text(Some(record.profile.clone())),
+        ExportColumn::FirstSeen => Cell::Time(Some(record.first_seen)),
+        ExportColumn::LastSeen => Cell::Time(Some(record.last_seen)),
+        ExportColumn::ClosedAt => Cell::Time(record.closed_at),
+    }
+}
+
+impl Cell {
+    fn to_text(&self) -> String {
+        match self {
+            Cell::Text(text) => text.clone().unwrap_or_default(),
+            Cell::Float(value) => value.map(|value| value.to_string()).unwrap_or_default(),
+            Cell::Int(value) => value.map(|value| value.to_string()).unwrap_or_default(),
+            Cell::Time(at) => at.map(|at| at.to_rfc3339()).unwrap_or_default(),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            Cell::Text(text) => text.clone().map(Value::String).unwrap_or(Value::Null),
+            Cell::Float(value) => value.map(Value::from).unwrap_or(Value::Null),
+            Cell::Int(value) => value.map(Value::from).unwrap_or(Value::Null),
+            Cell::Time(at) => at.map(|at| Value::String(at.to_rfc3339())).unwrap_or(Value::Null),
+        }
+    }
+}
+
+pub struct ExportService;
+
+impl ExportService {
+    /// Writes every record matching the filter, replacing files from earlier
+    /// exports. Returns each written file with its row count.
+    pub fn export(records: &[JobRecord], options: &ExportOptions) -> Result<Vec<(PathBuf, usize)>> {
+        let mut partitions: BTreeMap<Option<NaiveDate>, Vec<&JobRecord>> = BTreeMap::new();
+        for record in records.iter().filter(|record| options.filter.matches(record)) {
+            let day = options.partition_by_day.then(|| record.first_seen.date_naive());
+            partitions.entry(day).or_default().push(record);
+        }
+
+        let mut written = Vec::new();
+        for (day, mut records) in partitions {
+            records.sort_by_key(|record| record.first_seen);
+            let path = options.path_for(EXPORT_STEM, day);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            write_file(&path, options, &records).with_context(|| format!("Failed to write {}", path.display()))?;
+            written.push((path, records.len()));
+        }
+        Ok(written)
+    }
+
+    /// Appends each newly found job that matches the filter to
+    /// `new_jobs.<ext>` in the export directory until the event stream ends.
+    /// It has its own file so a one-off export doesn't replace it.
+    pub async fn run_sink(state: Arc<AppState>, options: Arc<ExportOptions>) {
+        info!("Exporting new jobs to {} as {}", options.dir.display(), options.format.extension());
+        let (mut last_id, mut receiver) = state.events.subscribe_from_now();
+
+        loop {
+            let events = match receiver.recv().await {
+                Ok(event) => vec![event],
+                // Catch up from the event backlog rather than leave a hole
+                Err(RecvError::Lagged(missed)) => {
+                    warn!("Export sink fell {} events behind, catching up", missed);
+                    let (backlog, fresh) = state.events.subscribe(Some(last_id));
+                    receiver = fresh;
+                    backlog
+                }
+                Err(RecvError::Closed) => return,
+            };
+
+            for event in events {
+                last_id = event.id;
+                if event.kind != JobEventKind::New || !options.filter.matches(&event.job) {
+                    continue;
+                }
+                if let Err(e) = append_record(&options, &event.job) {
+                    warn!("Failed to export job {}: {:#}", event.job.job.id, e);
+                }
+            }
+        }
+    }
+}
+
+fn write_file(path: &Path, options: &ExportOptions, records: &[&JobRecord]) -> Result<()> {
+    match options.format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_path(path)?;
+            writer.write_record(options.columns.iter().map(ExportColumn::name))?;
+            for record in records {
+                writer.write_record(options.columns.iter().map(|&column| cell(column, record).to_text()))?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Jsonl => {
+            let mut writer = BufWriter::new(File::create(path)?);
+            for record in records {
+                writeln!(writer, "{}", json_row(&options.columns, record))?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Parquet => write_parquet(path, &options.columns, records)?,
+    }
+    Ok(())
+}
+
+fn append_record(options: &ExportOptions, record: &JobRecord) -> Result<()> {
+    let day = options.partition_by_day.then(|| record.first_seen.date_naive());
+    let path = options.path_for(SINK_STEM, day);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let is_new = !path.exists();
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+    match options.format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(file);
+            if is_new {
+                writer.write_record(options.columns.iter().map(ExportColumn::name))?;
+            }
+            writer.write_record(options.columns.iter().map(|&column| cell(column, record).to_text()))?;
+            writer.flush()?;
+        }
+        ExportFormat::Jsonl => {
+            let mut file = file;
+            writeln!(file, "{}", json_row(&options.columns, record))?;
+        }
+        ExportFormat::Parquet => anyhow::bail!("Parquet files can't be appended to"),
+    }
+    Ok(())
+}
+
+fn json_row(columns: &[ExportColumn], record: &JobRecord) -> Value {
+    let row: Map<String, Value> = columns
+        .iter()
+        .map(|&column| (column.name().to_string(), cell(column, record).to_json()))
+        .collect();
+    Value::Object(row)
+}
+
+#[cfg(feature = "parquet")]
+fn write_parquet(path: &Path, columns: &[ExportColumn], records: &[&JobRecord]) -> Result<()> {
+    use parquet::basic::Compression;
+    use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+
+    // Every column is optional so they all share the same definition levels
+    let fields: Vec<String> = columns
+        .iter()
+        .map(|&column| {
+            let name = column.name();
+            match column {
+                ExportColumn::PayMin | ExportColumn::PayMax => format!("OPTIONAL DOUBLE {};", name),
+                ExportColumn::Shift => format!("OPTIONAL INT64 {};", name),
+                ExportColumn::FirstSeen | ExportColumn::LastSeen | ExportColumn::ClosedAt => {
+                    format!("OPTIONAL INT64 {} (TIMESTAMP(MILLIS,true));", name)
+                }
+                _ => format!("OPTIONAL BYTE_ARRAY {} (UTF8);", name),
+            }
+        })
+        .collect();
+    let schema = Arc::new(parse_message_type(&format!("message job {{ {} }}", fields.join(" ")))?);
+    let properties = Arc::new(WriterProperties::builder().set_compression(Compression::SNAPPY).build());
+
+    let mut writer = SerializedFileWriter::new(File::create(path)?, schema, properties)?;
+    let mut row_group = writer.next_row_group()?;
+    for &column in columns {
+        let Some(mut column_writer) = row_group.next_column()? else {
+            break;
+        };
+        let cells: Vec<Cell> = records.iter().map(|record| cell(column, record)).collect();
+        let levels: Vec<i16> = cells
+            .iter()
+            .map(|cell| match cell {
+                Cell::Text(value) => value.is_some(),
+                Cell::Float(value) => value.is_some(),
+                Cell::Int(value) => value.is_some(),
+                Cell::Time(value) => value.is_some(),
+            } as i16)
+            .collect();
+
+        match cells.first() {
+            Some(Cell::Float(_)) => {
+                let values: Vec<f64> = cells
+                    .iter()
+                    .filter_map(|cell| match cell {
+                        Cell::Float(value) => *value,
+                        _ => None,
+                    })
+                    .collect();
+                column_writer.typed::<DoubleType>().write_batch(&values, Some(&levels), None)?;
+            }
+            Some(Cell::Int(_)) | Some(Cell::Time(_)) => {
+                let values: Vec<i64> = cells
+                    .iter()
+                    .filter_map(|cell| match cell {
+                        Cell::Int(value) => *value,
+                        Cell::Time(at) => at.map(|at| at.timestamp_millis()),
+                        _ => None,
+                    })
+                    .collect();
+                column_writer.typed::<Int64Type>().write_batch(&values, Some(&levels), None)?;
+            }
+            _ => {
+                let values: Vec<ByteArray> = cells
+                    .iter()
+                    .filter_map(|cell| match cell {
+                        Cell::Text(text) => text.as_deref().map(ByteArray::from),
+                        _ => None,
+                    })
+                    .collect();
+                column_writer.typed::<ByteArrayType>().write_batch(&values, Some(&levels), None)?;
+            }
+        }
+        column_writer.close()?;
+    }
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "parquet"))]
+fn write_parquet(_path: &Path, _columns: &[ExportColumn], _records: &[&JobRecord]) -> Result<()> {
+    anyhow::bail!("Parquet export needs a build with `--features parquet`")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::JobInfo;
+    use crate::seen_set::SeenSet;
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn job(id: &str) -> JobInfo {
+        JobInfo {
+            id: id.into(),
+            title: "Warehouse Associate".into(),
+            location: "Brampton, ON".into(),
+            job_type: "Full Time".into(),
+            pay_min: Some(19.5),
+            pay_max: None,
+            shift: None,
+        }
+    }
+
+    fn options(dir: &TempDir) -> ExportOptions {
+        ExportOptions {
+            format: ExportFormat::Jsonl,
+            dir: dir.path().to_path_buf(),
+            partition_by_day: false,
+            columns: vec![ExportColumn::Id],
+            filter: JobFilter::default(),
+        }
+    }
+
+    fn lines(path: &Path) -> usize {
+        fs::read_to_string(path).map(|contents| contents.lines().count()).unwrap_or(0)
+    }
+
+    #[tokio::test]
+    async fn the_sink_catches_up_when_it_lags_before_its_first_event() {
+        let dir = TempDir::new().unwrap();
+        let options = Arc::new(options(&dir));
+        let state = Arc::new(AppState::new(SeenSet::new(), HashMap::new(), chrono::Duration::hours(6)));
+        let sink = tokio::spawn(ExportService::run_sink(state.clone(), options.clone()));
+        tokio::task::yield_now().await;
+
+        // More than the broadcast channel holds, so the first recv lags
+        let jobs: Vec<JobInfo> = (0..300).map(|n| job(&format!("J{}", n))).collect();
+        state.record_listed_jobs("default", &jobs);
+
+        let path = options.path_for(SINK_STEM, None);
+        for _ in 0..100 {
+            if lines(&path) == jobs.len() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        sink.abort();
+        assert_eq!(lines(&path), jobs.len());
+    }
+
+    #[test]
+    fn a_one_off_export_leaves_the_sink_file_alone() {
+        let dir = TempDir::new().unwrap();
+        let options = options(&dir);
+        let state = AppState::new(SeenSet::new(), HashMap::new(), chrono::Duration::hours(6));
+        state.record_listed_jobs("default", &[job("J1"), job("J2")]);
+        let history = state.job_history();
+
+        append_record(&options, &history[0]).unwrap();
+        let written = ExportService::export(&history, &options).unwrap();
+
+        assert_eq!(written, [(options.path_for(EXPORT_STEM, None), 2)]);
+        assert_eq!(lines(&options.path_for(SINK_STEM, None)), 1);
+    }
+}
@@ -0,0 +1,25 @@
+use anyhow::Result;
+use crate::config::HttpConfig;
+use crate::utils::load_ca_bundle;
+use reqwest::ClientBuilder;
+use std::time::Duration;
+
+/// A client builder with the `[http]` settings applied. Every outgoing
+/// service starts from this and adds whatever it needs on top, like a proxy.
+pub fn client_builder(config: &HttpConfig) -> Result<ClientBuilder> {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+        .read_timeout(Duration::from_millis(config.read_timeout_ms))
+        .timeout(Duration::from_millis(config.request_timeout_ms))
+        .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout_secs))
+        .pool_max_idle_per_host(config.pool_max_idle_per_host);
+    if !config.http2 {
+        builder = builder.http1_only();
+    }
+    if let Some(path) = &config.ca_bundle {
+        for certificate in load_ca_bundle(path)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    Ok(builder)
+}
@@ -1,5 +1,13 @@
 pub mod amazon_service;
+pub mod export_service;
+pub mod http_service;
 pub mod notification_service;
 pub mod persistence_service;
+pub mod proxy_service;
+pub mod report_service;
+pub mod scheduler_service;
+pub mod schema_service;
 pub mod shutdown_service;
-pub mod telegram_service;
\ No newline at end of file
+pub mod supervisor_service;
+pub mod telegram_service;
+pub mod token_service;
\ No newline at end of file
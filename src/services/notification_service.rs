@@ -1,30 +1,590 @@
+use anyhow::Result;
 use async_channel::{bounded, Receiver, Sender};
-use crate::model::{NotificationBatch};
-use crate::services::telegram_service::TelegramService;
-use log::info;
+use chrono::{DateTime, Local, Timelike, Utc};
+use crate::config::{DeliveryMode, QuietMode, Subscriber};
+use crate::metrics::{NOTIFICATIONS, NOTIFICATION_DURATION, NOTIFICATION_QUEUE_DEPTH, SEEN_JOBS};
+use crate::model::{
+    AppState, DeliveryOutcome, JobInfo, JobRecord, Notification, NotificationBatch, NotificationRecord,
+};
+use crate::services::telegram_service::{Rejected, TelegramService, Urgency};
+use crate::utils::time_in_range;
+use croner::Cron;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{info_span, Instrument};
+
+/// How often Telegram is probed for the health check, so readiness recovers
+/// even when there is nothing to send.
+const REACHABILITY_PROBE_INTERVAL: Duration = Duration::from_secs(60);
+/// How long held jobs wait after their release failed before trying again.
+const RELEASE_RETRY_DELAY: chrono::Duration = chrono::Duration::minutes(5);
 
 pub struct NotificationService {
-    sender: Sender<NotificationBatch>,
-    receiver: Receiver<NotificationBatch>,
+    sender: Sender<Notification>,
+    receiver: Receiver<Notification>,
+    subscribers: Vec<Subscriber>,
+    coalesce_window: chrono::Duration,
 }
 
 impl NotificationService {
-    pub fn new() -> Self {
+    pub fn new(subscribers: Vec<Subscriber>, coalesce_window: Duration) -> Self {
         let (sender, receiver) = bounded(100);
-        NotificationService { sender, receiver }
+        NotificationService {
+            sender,
+            receiver,
+            subscribers,
+            coalesce_window: chrono::Duration::from_std(coalesce_window).unwrap_or(chrono::Duration::MAX),
+        }
     }
 
-    pub fn sender(&self) -> Sender<NotificationBatch> {
+    pub fn sender(&self) -> Sender<Notification> {
         self.sender.clone()
     }
 
-    pub async fn run(&self, telegram_service: TelegramService) {
-        while let Ok(batch) = self.receiver.recv().await {
-            if let Err(e) = telegram_service.send_batch(&batch).await {
-                log::error!("Failed to send notification: {}", e);
+    pub async fn run(&self, telegram_service: TelegramService, state: &AppState) {
+        // The queues of a run that crashed are gone, so nothing would settle
+        // the jobs they held
+        let released = state.release_pending_deliveries();
+        if released > 0 {
+            warn!("Released {} jobs a previous notifier run left queued", released);
+        }
+
+        // Fires right away, so readiness doesn't wait for the first job
+        let mut probe = tokio::time::interval(REACHABILITY_PROBE_INTERVAL);
+        probe.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        let mut queues = Queues::default();
+        for subscriber in self.subscribers.iter().filter(|s| s.mode == DeliveryMode::Digest) {
+            if let Some(at) = next_occurrence(subscriber, &subscriber.digest_schedule, Utc::now()) {
+                info!("Next digest for {} at {}", subscriber.name, at.with_timezone(&Local).format("%Y-%m-%d %H:%M"));
+                queues.next_digest.insert(&subscriber.name, at);
+            }
+        }
+
+        loop {
+            let wait = queues
+                .next_digest
+                .values()
+                .chain(queues.next_release.values())
+                .chain(queues.outgoing.iter().map(|outgoing| &outgoing.send_at))
+                .min()
+                .map(|at| (*at - Utc::now()).to_std().unwrap_or(Duration::ZERO));
+
+            tokio::select! {
+                notification = self.receiver.recv() => {
+                    let Ok(notification) = notification else { break };
+                    NOTIFICATION_QUEUE_DEPTH.set(self.receiver.len() as i64);
+                    match notification {
+                        Notification::Jobs(batch) => {
+                            self.route_batch(state, batch, &mut queues, Utc::now());
+                        }
+                        Notification::Alert(text) => {
+                            let timer = NOTIFICATION_DURATION.start_timer();
+                            let span = info_span!("notify", kind = "alert");
+                            let result = telegram_service.send_operator_alert(&text).instrument(span.clone()).await;
+                            let _entered = span.enter();
+                            match &result {
+                                Err(e) => log::error!("Failed to send operator alert: {}", e),
+                                Ok(()) => info!("Sent operator alert"),
+                            }
+                            timer.observe_duration();
+                            record(state, "alert", text, &result);
+                        }
+                        Notification::Report(text) => {
+                            let timer = NOTIFICATION_DURATION.start_timer();
+                            let span = info_span!("notify", kind = "report");
+                            let result = telegram_service.send_report(&text).instrument(span.clone()).await;
+                            let _entered = span.enter();
+                            match &result {
+                                Err(e) => log::error!("Failed to send report: {}", e),
+                                Ok(()) => info!("Sent weekly report"),
+                            }
+                            timer.observe_duration();
+                            record(state, "report", "weekly report".to_string(), &result);
+                        }
+                    }
+                    NOTIFICATION_QUEUE_DEPTH.set(self.receiver.len() as i64);
+                }
+                _ = probe.tick() => {
+                    let was_reachable = state.health.notifier_reachable();
+                    match telegram_service.check_reachable().await {
+                        Ok(()) => {
+                            if !was_reachable {
+                                info!("Telegram is reachable");
+                            }
+                            state.health.set_notifier_reachable(true);
+                        }
+                        Err(e) => {
+                            warn!("Telegram is not reachable: {}", e);
+                            state.health.set_notifier_reachable(false);
+                        }
+                    }
+                }
+                _ = sleep_for(wait) => {
+                    let now = Utc::now();
+                    let (due, waiting) = std::mem::take(&mut queues.outgoing)
+                        .into_iter()
+                        .partition(|outgoing| outgoing.send_at <= now);
+                    queues.outgoing = waiting;
+                    for outgoing in due {
+                        self.send_batch(&telegram_service, state, outgoing).await;
+                    }
+
+                    for subscriber in &self.subscribers {
+                        let name = subscriber.name.as_str();
+
+                        if queues.next_digest.get(name).is_some_and(|at| *at <= now) {
+                            let ids = queues.digest.remove(name).unwrap_or_default();
+                            let quiet = in_quiet_hours(subscriber, now);
+                            if !ids.is_empty() {
+                                if quiet && subscriber.quiet_mode == QuietMode::Hold {
+                                    hold(&mut queues, subscriber, ids, now);
+                                } else {
+                                    let urgency = if quiet { Urgency::Silent } else { Urgency::Normal };
+                                    let retry =
+                                        self.send_digest(&telegram_service, state, subscriber, &ids, urgency).await;
+                                    queue(queues.digest.entry(name).or_default(), retry);
+                                }
+                            }
+                            match next_occurrence(subscriber, &subscriber.digest_schedule, now) {
+                                Some(at) => queues.next_digest.insert(name, at),
+                                None => queues.next_digest.remove(name),
+                            };
+                        }
+
+                        if queues.next_release.get(name).is_some_and(|at| *at <= now) {
+                            queues.next_release.remove(name);
+                            let ids = queues.held.remove(name).unwrap_or_default();
+                            let retry =
+                                self.send_digest(&telegram_service, state, subscriber, &ids, Urgency::Normal).await;
+                            if !retry.is_empty() {
+                                queue(queues.held.entry(name).or_default(), retry);
+                                queues.next_release.insert(name, now + RELEASE_RETRY_DELAY);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Queues the jobs each subscriber's filter accepts for its next message,
+    /// digest or the end of its quiet hours. Priority jobs always go in the
+    /// next message. Digests and held jobs keep what failed for their next
+    /// attempt. A failed message isn't retried here: its jobs are released
+    /// and come back, for the subscribers that missed them, with a later
+    /// poll that lists them.
+    fn route_batch<'a>(
+        &'a self,
+        state: &AppState,
+        batch: NotificationBatch,
+        queues: &mut Queues<'a>,
+        now: DateTime<Utc>,
+    ) {
+        // Register every message a job goes out in before any can complete
+        let mut recipients: HashMap<&str, Vec<&str>> = HashMap::new();
+        for job in &batch.jobs {
+            let matching = self
+                .subscribers
+                .iter()
+                .filter(|s| s.filter.matches_job(job))
+                .map(|s| s.name.as_str())
+                .collect();
+            recipients.insert(&job.id, state.expect_deliveries(&job.id, matching));
+        }
+
+        for subscriber in &self.subscribers {
+            let name = subscriber.name.as_str();
+            let (priority, regular): (Vec<_>, Vec<_>) = batch
+                .jobs
+                .iter()
+                .filter(|job| recipients[job.id.as_str()].contains(&name))
+                .cloned()
+                .partition(|job| subscriber.priority.matches(job));
+
+            if !priority.is_empty() {
+                self.coalesce(queues, subscriber, &batch.location, priority, Urgency::Priority, now);
+            }
+            if regular.is_empty() {
+                continue;
+            }
+
+            let quiet = in_quiet_hours(subscriber, now);
+            let ids = regular.iter().map(|job| job.id.clone());
+            if subscriber.mode == DeliveryMode::Digest {
+                queue(queues.digest.entry(name).or_default(), ids);
+            } else if quiet && subscriber.quiet_mode == QuietMode::Hold {
+                hold(queues, subscriber, ids, now);
             } else {
-                info!("Sent notification for {} jobs in {}", batch.jobs.len(), batch.location);
+                let urgency = if quiet { Urgency::Silent } else { Urgency::Normal };
+                self.coalesce(queues, subscriber, &batch.location, regular, urgency, now);
+            }
+        }
+    }
+
+    /// Adds the jobs to the message going to the subscriber for this location,
+    /// starting one that goes out when the coalescing window closes. Priority
+    /// jobs go out right away.
+    fn coalesce<'a>(
+        &self,
+        queues: &mut Queues<'a>,
+        subscriber: &'a Subscriber,
+        location: &str,
+        jobs: Vec<JobInfo>,
+        urgency: Urgency,
+        now: DateTime<Utc>,
+    ) {
+        let existing = queues.outgoing.iter_mut().find(|outgoing| {
+            outgoing.subscriber.name == subscriber.name
+                && outgoing.batch.location == location
+                && outgoing.urgency == urgency
+        });
+        match existing {
+            Some(outgoing) => {
+                for job in jobs {
+                    if !outgoing.batch.jobs.iter().any(|queued| queued.id == job.id) {
+                        outgoing.batch.jobs.push(job);
+                    }
+                }
+            }
+            None => queues.outgoing.push(Outgoing {
+                subscriber,
+                urgency,
+                batch: NotificationBatch { location: location.to_string(), jobs },
+                send_at: if urgency == Urgency::Priority {
+                    now
+                } else {
+                    now.checked_add_signed(self.coalesce_window).unwrap_or(DateTime::<Utc>::MAX_UTC)
+                },
+            }),
+        }
+    }
+
+    /// Sends a coalesced message, split if it's too long for one. Jobs in
+    /// the parts that went out are done even if a later part fails.
+    async fn send_batch(&self, telegram_service: &TelegramService, state: &AppState, outgoing: Outgoing<'_>) {
+        let Outgoing { subscriber, urgency, batch, .. } = outgoing;
+        let timer = NOTIFICATION_DURATION.start_timer();
+        let span = info_span!(
+            "notify",
+            kind = "jobs",
+            subscriber = %subscriber.name,
+            location = %batch.location,
+            jobs = batch.jobs.len(),
+            urgency = ?urgency
+        );
+        let (sent, result) = telegram_service
+            .send_batch(&subscriber.chat_id, &batch, urgency)
+            .instrument(span.clone())
+            .await;
+        let _entered = span.enter();
+        match &result {
+            Err(e) => log::error!(
+                "Failed to send notification to {} after {} of {} jobs went out: {}",
+                subscriber.name,
+                sent.len(),
+                batch.jobs.len(),
+                e
+            ),
+            Ok(()) => info!("Sent {} {} jobs in {}", subscriber.name, batch.jobs.len(), batch.location),
+        }
+        timer.observe_duration();
+        let summary = format!("{} jobs in {} for {}", batch.jobs.len(), batch.location, subscriber.name);
+        record(state, "jobs", summary, &result);
+        let (delivered, failed): (Vec<String>, Vec<String>) =
+            batch.jobs.iter().map(|job| job.id.clone()).partition(|id| sent.contains(&id.as_str()));
+        settle(state, subscriber, &delivered, DeliveryOutcome::Delivered);
+        settle(state, subscriber, &failed, outcome_of(&result));
+    }
+
+    /// Looks the jobs up again so the digest shows the ones that closed in
+    /// the meantime. Returns the jobs that didn't go out but are worth
+    /// another attempt, which stay pending for the caller to send again.
+    async fn send_digest(
+        &self,
+        telegram_service: &TelegramService,
+        state: &AppState,
+        subscriber: &Subscriber,
+        ids: &[String],
+        urgency: Urgency,
+    ) -> Vec<String> {
+        let records: Vec<JobRecord> = ids.iter().filter_map(|id| state.job_record(id)).collect();
+        if records.is_empty() {
+            settle(state, subscriber, ids, DeliveryOutcome::Delivered);
+            return Vec::new();
+        }
+
+        let timer = NOTIFICATION_DURATION.start_timer();
+        let span = info_span!("notify", kind = "digest", subscriber = %subscriber.name, jobs = records.len());
+        let (sent, result) =
+            telegram_service.send_digest(&subscriber.chat_id, &records, urgency).instrument(span.clone()).await;
+        let _entered = span.enter();
+        match &result {
+            Err(e) => log::error!(
+                "Failed to send digest to {} after {} of {} jobs went out: {}",
+                subscriber.name,
+                sent.len(),
+                records.len(),
+                e
+            ),
+            Ok(()) => info!("Sent {} a digest of {} jobs", subscriber.name, records.len()),
+        }
+        timer.observe_duration();
+        record(state, "digest", format!("digest of {} jobs for {}", records.len(), subscriber.name), &result);
+        // Jobs in the parts that went out are done, only the rest is retried
+        let (failed, delivered): (Vec<String>, Vec<String>) = ids.iter().cloned().partition(|id| {
+            !sent.contains(&id.as_str()) && records.iter().any(|record| record.job.id == *id)
+        });
+        settle(state, subscriber, &delivered, DeliveryOutcome::Delivered);
+        match outcome_of(&result) {
+            DeliveryOutcome::Failed => {
+                let (retry, sent) = state.retry_later(&failed, &subscriber.name);
+                SEEN_JOBS.add(sent as i64);
+                retry
+            }
+            outcome => {
+                settle(state, subscriber, &failed, outcome);
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// Jobs waiting on a schedule, kept in memory only.
+#[derive(Default)]
+struct Queues<'a> {
+    /// Job IDs for each digest subscriber's next digest.
+    digest: HashMap<&'a str, Vec<String>>,
+    next_digest: HashMap<&'a str, DateTime<Utc>>,
+    /// Job IDs held back until quiet hours end.
+    held: HashMap<&'a str, Vec<String>>,
+    next_release: HashMap<&'a str, DateTime<Utc>>,
+    /// Messages collecting jobs until their coalescing window closes.
+    outgoing: Vec<Outgoing<'a>>,
+}
+
+struct Outgoing<'a> {
+    subscriber: &'a Subscriber,
+    urgency: Urgency,
+    batch: NotificationBatch,
+    send_at: DateTime<Utc>,
+}
+
+fn queue(queued: &mut Vec<String>, job_ids: impl IntoIterator<Item = String>) {
+    for job_id in job_ids {
+        if !queued.contains(&job_id) {
+            queued.push(job_id);
+        }
+    }
+}
+
+/// Holds the jobs back until the subscriber's quiet hours end.
+fn hold<'a>(
+    queues: &mut Queues<'a>,
+    subscriber: &'a Subscriber,
+    job_ids: impl IntoIterator<Item = String>,
+    now: DateTime<Utc>,
+) {
+    let name = subscriber.name.as_str();
+    queue(queues.held.entry(name).or_default(), job_ids);
+    if queues.next_release.contains_key(name) {
+        return;
+    }
+    let (_, end) = subscriber.quiet_hours.expect("only set with quiet hours");
+    let end = Cron::from_str(&format!("{} {} * * *", end.minute(), end.hour()))
+        .expect("a time of day is a valid pattern");
+    if let Some(at) = next_occurrence(subscriber, &end, now) {
+        info!("Holding jobs for {} until {}", name, at.with_timezone(&Local).format("%Y-%m-%d %H:%M"));
+        queues.next_release.insert(name, at);
+    }
+}
+
+fn settle(state: &AppState, subscriber: &Subscriber, job_ids: &[String], outcome: DeliveryOutcome) {
+    if job_ids.is_empty() {
+        return;
+    }
+    let sent = state.record_delivery(job_ids, &subscriber.name, outcome);
+    SEEN_JOBS.add(sent as i64);
+    match outcome {
+        DeliveryOutcome::Delivered => {}
+        DeliveryOutcome::Rejected => {
+            warn!("Telegram refused {} jobs for {}, they won't be sent again", job_ids.len(), subscriber.name)
+        }
+        DeliveryOutcome::Failed => {
+            warn!("{} jobs go to {} again with a later poll that lists them", job_ids.len(), subscriber.name)
+        }
+    }
+}
+
+/// A rate limit or an unreachable Telegram is worth another try, a chat
+/// that blocked the bot or a message Telegram can't parse isn't.
+fn outcome_of(result: &Result<()>) -> DeliveryOutcome {
+    match result {
+        Ok(()) => DeliveryOutcome::Delivered,
+        Err(e) if e.downcast_ref::<Rejected>().is_some_and(Rejected::is_final) => DeliveryOutcome::Rejected,
+        Err(_) => DeliveryOutcome::Failed,
+    }
+}
+
+/// Keeps the health check, dashboard history and metrics up to date.
+fn record(state: &AppState, kind: &'static str, summary: String, result: &Result<()>) {
+    // One refused message doesn't mean Telegram is down
+    if result.as_ref().err().is_none_or(|e| !e.is::<Rejected>()) {
+        state.health.set_notifier_reachable(result.is_ok());
+    }
+    state.record_notification(NotificationRecord {
+        at: Utc::now(),
+        kind,
+        summary,
+        delivered: result.is_ok(),
+        error: result.as_ref().err().map(|e| e.to_string()),
+    });
+    let result = if result.is_ok() { "success" } else { "failure" };
+    NOTIFICATIONS.with_label_values(&[kind, result]).inc();
+}
+
+/// The next time after `after` that `schedule` matches in the subscriber's
+/// time zone.
+fn next_occurrence(subscriber: &Subscriber, schedule: &Cron, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let next = match subscriber.timezone {
+        Some(tz) => schedule
+            .find_next_occurrence(&after.with_timezone(&tz), false)
+            .map(|at| at.with_timezone(&Utc)),
+        None => schedule
+            .find_next_occurrence(&after.with_timezone(&Local), false)
+            .map(|at| at.with_timezone(&Utc)),
+    };
+    match next {
+        Ok(at) => Some(at),
+        Err(e) => {
+            warn!("`{}` never matches again for {}: {}", schedule.pattern, subscriber.name, e);
+            None
+        }
+    }
+}
+
+fn in_quiet_hours(subscriber: &Subscriber, now: DateTime<Utc>) -> bool {
+    let Some(range) = subscriber.quiet_hours else {
+        return false;
+    };
+    let time = match subscriber.timezone {
+        Some(tz) => now.with_timezone(&tz).time(),
+        None => now.with_timezone(&Local).time(),
+    };
+    time_in_range(time, range)
+}
+
+async fn sleep_for(wait: Option<Duration>) {
+    match wait {
+        Some(wait) => tokio::time::sleep(wait).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PriorityRule;
+    use crate::seen_set::SeenSet;
+    use chrono::{NaiveTime, TimeZone};
+
+    fn subscriber(timezone: &str) -> Subscriber {
+        Subscriber {
+            name: "alice".into(),
+            chat_id: "42".into(),
+            mode: DeliveryMode::Digest,
+            digest_schedule: Cron::from_str("0 8 * * *").unwrap(),
+            timezone: Some(timezone.parse().unwrap()),
+            filter: Default::default(),
+            quiet_hours: None,
+            quiet_mode: QuietMode::Silent,
+            priority: Default::default(),
+        }
+    }
+
+    fn utc(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn digests_follow_the_subscribers_clock_across_dst() {
+        let subscriber = subscriber("America/Toronto");
+        // 09:00 EST, then 08:00 EDT the morning the clocks go forward
+        assert_eq!(next_occurrence(&subscriber, &subscriber.digest_schedule, utc(9, 14)), Some(utc(10, 12)));
+        // A digest due right now is the one after
+        assert_eq!(next_occurrence(&subscriber, &subscriber.digest_schedule, utc(11, 12)), Some(utc(12, 12)));
+    }
+
+    fn job(id: &str, pay_max: f64) -> JobInfo {
+        JobInfo {
+            id: id.into(),
+            title: "Warehouse Associate".into(),
+            location: "Brampton, ON".into(),
+            job_type: "FULL_TIME".into(),
+            pay_min: None,
+            pay_max: Some(pay_max),
+            shift: None,
+        }
+    }
+
+    fn night_owl(mode: DeliveryMode) -> Subscriber {
+        Subscriber {
+            mode,
+            quiet_hours: Some((NaiveTime::from_hms_opt(22, 0, 0).unwrap(), NaiveTime::from_hms_opt(7, 0, 0).unwrap())),
+            quiet_mode: QuietMode::Hold,
+            priority: PriorityRule { min_pay: Some(25.0), ..Default::default() },
+            ..subscriber("America/Toronto")
+        }
+    }
+
+    #[test]
+    fn quiet_hours_are_read_in_the_subscribers_time_zone() {
+        let owl = night_owl(DeliveryMode::Instant);
+        // 21:00 and 23:00 EST, then 06:00 and 07:00 EDT
+        assert!(!in_quiet_hours(&owl, utc(9, 2)));
+        assert!(in_quiet_hours(&owl, utc(9, 4)));
+        assert!(in_quiet_hours(&owl, utc(10, 10)));
+        assert!(!in_quiet_hours(&owl, utc(10, 11)));
+        assert!(!in_quiet_hours(&subscriber("America/Toronto"), utc(9, 4)));
+    }
+
+    #[tokio::test]
+    async fn priority_jobs_go_out_at_once_during_quiet_hours() {
+        for mode in [DeliveryMode::Instant, DeliveryMode::Digest] {
+            let service = NotificationService::new(vec![night_owl(mode)], Duration::from_secs(5));
+            let state = AppState::new(SeenSet::new(), HashMap::new(), chrono::Duration::hours(6));
+            let jobs = vec![job("J1", 30.0), job("J2", 20.0)];
+            state.claim_new_jobs("default", &jobs);
+            let batch = NotificationBatch { location: "Brampton, ON".into(), jobs };
+            let mut queues = Queues::default();
+            // 23:00 EST
+            let now = utc(9, 4);
+
+            service.route_batch(&state, batch, &mut queues, now);
+
+            assert_eq!(queues.outgoing.len(), 1);
+            let outgoing = &queues.outgoing[0];
+            assert_eq!(outgoing.urgency, Urgency::Priority);
+            assert_eq!(outgoing.send_at, now);
+            assert_eq!(outgoing.batch.jobs.iter().map(|job| job.id.as_str()).collect::<Vec<_>>(), ["J1"]);
+            let waiting = match mode {
+                DeliveryMode::Instant => &queues.held,
+                DeliveryMode::Digest => &queues.digest,
+            };
+            assert_eq!(waiting["alice"], ["J2"]);
+            if mode == DeliveryMode::Instant {
+                // 07:00 EST the next morning
+                assert_eq!(queues.next_release["alice"], utc(9, 12));
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn a_schedule_that_never_matches_has_no_next_digest() {
+        let subscriber = subscriber("UTC");
+        let never = Cron::from_str("0 8 30 2 *").unwrap();
+        assert_eq!(next_occurrence(&subscriber, &never, utc(9, 14)), None);
+    }
+}
@@ -1,54 +1,332 @@
 use anyhow::Result;
-use crate::model::AppState;
-use crate::config::Config;
-use log::{info, warn};
-use std::collections::HashSet;
-use std::fs;
+use crate::model::{AppState, JobRecord, SeenChange};
+use crate::config::{Config, PersistenceConfig};
+use crate::metrics::SEEN_JOBS;
+use log::{debug, info, warn};
+use crate::seen_set::SeenSet;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
 use std::time::Duration;
-use tokio::time;
+use tokio::{task, time};
 use std::sync::Arc;
 
 pub struct PersistenceService;
 
 impl PersistenceService {
+    /// Appends seen-set changes to the journal every `journal_flush_ms` and
+    /// folds the journal into a fresh snapshot every `persist_interval_secs`.
     pub async fn run(state: Arc<AppState>, config: Config) {
-        let mut interval = time::interval(Duration::from_secs(config.persistence.persist_interval_secs));
-        
+        let persistence = &config.persistence;
+        let mut flush = time::interval(Duration::from_millis(persistence.journal_flush_ms));
+        let mut compact = time::interval(Duration::from_secs(persistence.persist_interval_secs));
+        // Changes a failed journal write still owes
+        let mut unwritten = Vec::new();
+
         while !state.shutdown_flag.load(std::sync::atomic::Ordering::Relaxed) {
-            interval.tick().await;
-            
-            let jobs = state.get_seen_jobs().await;
-            match Self::save_seen_jobs(&config.persistence.seen_jobs_file, &jobs) {
-                Ok(_) => info!("Persisted {} seen jobs to disk", jobs.len()),
-                Err(e) => warn!("Failed to persist jobs: {}", e),
+            tokio::select! {
+                _ = flush.tick() => {
+                    Self::flush_journal(&state, persistence, &mut unwritten).await;
+                }
+                _ = compact.tick() => {
+                    Self::flush_journal(&state, persistence, &mut unwritten).await;
+                    Self::compact(&state, persistence, &mut unwritten).await;
+                    if let Err(e) = Self::persist_history(&state, persistence).await {
+                        warn!("Failed to persist job history: {}", e);
+                    }
+                }
             }
         }
-        
+
         // Final persistence on shutdown
-        let jobs = state.get_seen_jobs().await;
-        if let Err(e) = Self::save_seen_jobs(&config.persistence.seen_jobs_file, &jobs) {
-            warn!("Final persistence failed: {}", e);
-        }
-    }
-
-    pub fn load_seen_jobs(path: &str) -> Result<HashSet<String>> {
-        fs::read_to_string(path)
-            .map(|contents| {
-                contents
-                    .lines()
-                    .map(|s| s.trim().to_string())
-                    .filter(|s| !s.is_empty())
-                    .collect()
-            })
-            .or_else(|_| {
+        Self::flush_journal(&state, persistence, &mut unwritten).await;
+        Self::compact(&state, persistence, &mut unwritten).await;
+        if let Err(e) = Self::persist_history(&state, persistence).await {
+            warn!("Final job history persistence failed: {}", e);
+        }
+        info!("Persistence stopped");
+    }
+
+    async fn flush_journal(state: &AppState, persistence: &PersistenceConfig, unwritten: &mut Vec<SeenChange>) {
+        unwritten.extend(state.take_seen_changes());
+        if unwritten.is_empty() {
+            return;
+        }
+        let path = persistence.journal_file.clone();
+        let changes = unwritten.clone();
+        match blocking(move || Self::append_journal(&path, &changes)).await {
+            Ok(()) => {
+                debug!("Journaled {} seen-set changes", unwritten.len());
+                unwritten.clear();
+            }
+            Err(e) => warn!("Failed to append to {}, will retry: {}", persistence.journal_file, e),
+        }
+    }
+
+    /// Writes a snapshot and starts the journal over with just the
+    /// deliveries of unfinished jobs, which the snapshot doesn't hold.
+    /// Changes made meanwhile stay queued for the next journal flush.
+    async fn compact(state: &Arc<AppState>, persistence: &PersistenceConfig, unwritten: &mut Vec<SeenChange>) {
+        let path = persistence.seen_jobs_file.clone();
+        let snapshot_state = state.clone();
+        match blocking(move || Self::save_seen_jobs(&path, &snapshot_state.seen_jobs)).await {
+            Ok(count) => {
+                SEEN_JOBS.set(count as i64);
+                info!("Persisted {} seen jobs to disk", count);
+                // The snapshot covers anything the journal failed to take
+                unwritten.clear();
+                let journal = persistence.journal_file.clone();
+                let deliveries = state.unfinished_deliveries();
+                if let Err(e) = blocking(move || Self::rewrite_journal(&journal, &deliveries)).await {
+                    warn!("Failed to start {} over: {}", persistence.journal_file, e);
+                }
+            }
+            Err(e) => warn!("Failed to persist jobs: {}", e),
+        }
+    }
+
+    async fn persist_history(state: &AppState, persistence: &PersistenceConfig) -> Result<()> {
+        let path = persistence.history_file.clone();
+        let history = state.job_history();
+        blocking(move || Self::save_job_history(&path, &history)).await
+    }
+
+    fn append_journal(path: &str, changes: &[SeenChange]) -> Result<()> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let mut writer = BufWriter::new(file);
+        Self::write_changes(&mut writer, changes)?;
+        writer.flush()?;
+        writer.get_ref().sync_data()?;
+        Ok(())
+    }
+
+    /// Swapped in whole, so a crash can't leave an empty journal behind
+    /// while the snapshot lacks what it held.
+    fn rewrite_journal(path: &str, changes: &[SeenChange]) -> Result<()> {
+        let temp_path = format!("{}.tmp", path);
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        Self::write_changes(&mut writer, changes)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    fn write_changes(writer: &mut impl Write, changes: &[SeenChange]) -> Result<()> {
+        for change in changes {
+            match change {
+                SeenChange::Added(id) => writeln!(writer, "+{}", id)?,
+                SeenChange::Removed(id) => writeln!(writer, "-{}", id)?,
+                SeenChange::Delivered { job_id, subscriber } => writeln!(writer, ">{} {}", job_id, subscriber)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// The snapshot with the journal replayed on top, and the subscribers
+    /// each job that isn't seen yet already reached. A torn last journal
+    /// line from a crash mid-write is ignored.
+    pub fn load_seen_jobs(
+        snapshot_path: &str,
+        journal_path: &str,
+    ) -> Result<(SeenSet, HashMap<String, HashSet<String>>)> {
+        let seen: SeenSet = match fs::read_to_string(snapshot_path) {
+            Ok(contents) => contents
+                .lines()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            Err(_) => {
                 warn!("No seen jobs file found, starting fresh");
-                Ok(HashSet::new())
-            })
+                SeenSet::new()
+            }
+        };
+
+        let mut delivered: HashMap<String, HashSet<String>> = HashMap::new();
+        let Ok(journal) = fs::read_to_string(journal_path) else {
+            return Ok((seen, delivered));
+        };
+        let complete = match journal.rfind('\n') {
+            Some(end) => &journal[..end],
+            None => "",
+        };
+        let mut replayed = 0;
+        for (number, line) in complete.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let line = line.trim();
+            if let Some(id) = line.strip_prefix('+').filter(|id| !id.is_empty()) {
+                seen.insert(id.to_string());
+                delivered.remove(id);
+            } else if let Some(id) = line.strip_prefix('-').filter(|id| !id.is_empty()) {
+                // Marked unseen to go out again, to everybody
+                seen.remove(id);
+                delivered.remove(id);
+            } else if let Some((id, subscriber)) = line.strip_prefix('>').and_then(|entry| entry.split_once(' ')) {
+                delivered.entry(id.to_string()).or_default().insert(subscriber.to_string());
+            } else {
+                warn!("Skipping line {} of {}: `{}`", number + 1, journal_path, line);
+                continue;
+            }
+            replayed += 1;
+        }
+        info!("Replayed {} journal entries from {}", replayed, journal_path);
+        delivered.retain(|id, _| !seen.contains(id));
+        Ok((seen, delivered))
     }
 
-    fn save_seen_jobs(path: &str, seen_jobs: &HashSet<String>) -> Result<()> {
-        let data: Vec<&str> = seen_jobs.iter().map(|s| s.as_str()).collect();
-        fs::write(path, data.join("\n"))?;
+    /// Writes the IDs out a shard at a time instead of copying the whole
+    /// set, and swaps the file in whole so a crash mid-write keeps the old
+    /// one.
+    fn save_seen_jobs(path: &str, seen_jobs: &SeenSet) -> Result<usize> {
+        let temp_path = format!("{}.tmp", path);
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        let mut count = 0;
+        let mut result = Ok(());
+        seen_jobs.for_each(|id| {
+            if result.is_ok() {
+                result = writeln!(writer, "{}", id);
+                count += 1;
+            }
+        });
+        result?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        fs::rename(&temp_path, path)?;
+        Ok(count)
+    }
+
+    /// One JSON record per line. Lines that no longer parse are skipped so a
+    /// partly written file doesn't cost us the rest of the history.
+    pub fn load_job_history(path: &str) -> Result<HashMap<String, JobRecord>> {
+        let Ok(contents) = fs::read_to_string(path) else {
+            warn!("No job history file found, starting fresh");
+            return Ok(HashMap::new());
+        };
+
+        let mut history = HashMap::new();
+        for (number, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            match serde_json::from_str::<JobRecord>(line) {
+                Ok(record) => {
+                    history.insert(record.job.id.clone(), record);
+                }
+                Err(e) => warn!("Skipping line {} of {}: {}", number + 1, path, e),
+            }
+        }
+        Ok(history)
+    }
+
+    /// Swapped in whole like the seen set, so a crash mid-write can't leave
+    /// a truncated history behind.
+    fn save_job_history(path: &str, history: &[JobRecord]) -> Result<()> {
+        let temp_path = format!("{}.tmp", path);
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        for record in history {
+            serde_json::to_writer(&mut writer, record)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        fs::rename(&temp_path, path)?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Runs file writes and fsyncs on the blocking pool instead of stalling the
+/// async workers.
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    task::spawn_blocking(work).await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn load(snapshot: &str, journal: &str) -> SeenSet {
+        let dir = TempDir::new().unwrap();
+        let snapshot_path = dir.path().join("seen_jobs.txt");
+        let journal_path = dir.path().join("seen_jobs.journal");
+        fs::write(&snapshot_path, snapshot).unwrap();
+        fs::write(&journal_path, journal).unwrap();
+        PersistenceService::load_seen_jobs(snapshot_path.to_str().unwrap(), journal_path.to_str().unwrap()).unwrap().0
+    }
+
+    fn sorted(seen: &SeenSet) -> Vec<String> {
+        let mut ids = Vec::new();
+        seen.for_each(|id| ids.push(id.to_string()));
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn the_journal_is_replayed_over_the_snapshot() {
+        let seen = load("J1\nJ2\n\nJ3\n", "+J4\n-J2\n+J5\n-J5\n");
+        assert_eq!(sorted(&seen), ["J1", "J3", "J4"]);
+    }
+
+    #[test]
+    fn a_torn_last_journal_line_is_skipped() {
+        let seen = load("J1\n", "+J2\n-J1\n+J3");
+        assert_eq!(sorted(&seen), ["J2"]);
+    }
+
+    #[test]
+    fn unknown_journal_lines_are_skipped() {
+        let seen = load("J1\n", "J2\n+\n+J3\n");
+        assert_eq!(sorted(&seen), ["J1", "J3"]);
+    }
+
+    #[test]
+    fn deliveries_are_kept_only_for_jobs_not_seen_yet() {
+        let dir = TempDir::new().unwrap();
+        let snapshot_path = dir.path().join("seen_jobs.txt");
+        let journal_path = dir.path().join("seen_jobs.journal");
+        fs::write(&snapshot_path, "J1\n").unwrap();
+        fs::write(&journal_path, ">J2 instant\n>J2 night owl\n>J3 instant\n+J3\n>J4 instant\n-J4\n").unwrap();
+
+        let (seen, delivered) =
+            PersistenceService::load_seen_jobs(snapshot_path.to_str().unwrap(), journal_path.to_str().unwrap()).unwrap();
+
+        assert_eq!(sorted(&seen), ["J1", "J3"]);
+        let subscribers = HashSet::from(["instant".to_string(), "night owl".to_string()]);
+        assert_eq!(delivered, HashMap::from([("J2".to_string(), subscribers)]));
+    }
+
+    #[test]
+    fn missing_files_start_empty() {
+        let dir = TempDir::new().unwrap();
+        let missing = dir.path().join("missing").to_str().unwrap().to_string();
+        let (seen, delivered) = PersistenceService::load_seen_jobs(&missing, &missing).unwrap();
+        assert_eq!(seen.len(), 0);
+        assert!(delivered.is_empty());
+    }
+
+    #[test]
+    fn job_history_round_trips_through_a_swapped_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("job_history.jsonl");
+        let path = path.to_str().unwrap();
+        fs::write(path, "stale\n").unwrap();
+        let at = chrono::Utc::now();
+        let record = JobRecord {
+            job: crate::model::JobInfo {
+                id: "J1".into(),
+                title: "Warehouse Associate".into(),
+                location: "Brampton, ON".into(),
+                job_type: "Full Time".into(),
+                pay_min: Some(19.5),
+                pay_max: None,
+                shift: None,
+            },
+            profile: "default".into(),
+            first_seen: at,
+            last_seen: at,
+            closed_at: None,
+        };
+
+        PersistenceService::save_job_history(path, &[record]).unwrap();
+        let history = PersistenceService::load_job_history(path).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history["J1"].profile, "default");
+        assert!(!fs::exists(format!("{}.tmp", path)).unwrap());
+    }
+}
@@ -0,0 +1,217 @@
+use crate::config::{HttpConfig, ProxyConfig, ProxySelection};
+use crate::metrics::{PROXIES_AVAILABLE, PROXY_EJECTIONS};
+use crate::services::http_service::client_builder;
+use log::{info, warn};
+use reqwest::{Client, Proxy, Url};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct ProxyState {
+    client: Client,
+    /// Scheme, host and port only, safe to log.
+    label: String,
+    last_used: Option<Instant>,
+    /// Failures since the last success. Reaching the limit ejects the proxy.
+    failures: u32,
+    ejected_until: Option<Instant>,
+}
+
+/// A proxy picked for one request. Hand it back through
+/// [`ProxyPool::report`] once the request is done.
+pub struct ProxyLease {
+    index: usize,
+    pub client: Client,
+    pub label: String,
+}
+
+/// The egress proxies requests are spread over, or a single direct client
+/// when none are configured. Proxies that keep failing sit out for a while;
+/// if every proxy is out, the one due back first is used anyway rather than
+/// stalling polls.
+pub struct ProxyPool {
+    selection: ProxySelection,
+    eject_after_failures: u32,
+    eject_for: Duration,
+    next: Mutex<usize>,
+    proxies: Mutex<Vec<ProxyState>>,
+}
+
+impl ProxyPool {
+    pub fn new(config: &ProxyConfig, http: &HttpConfig) -> anyhow::Result<Self> {
+        let mut proxies = Vec::new();
+        for url in &config.urls {
+            let client = client_builder(http)?.proxy(Proxy::all(url.expose())?).build()?;
+            proxies.push(ProxyState::new(client, proxy_label(url.expose())));
+        }
+        if proxies.is_empty() {
+            proxies.push(ProxyState::new(client_builder(http)?.build()?, "direct".into()));
+        } else {
+            info!("Routing Amazon requests through {} proxies", proxies.len());
+        }
+        PROXIES_AVAILABLE.set(proxies.len() as i64);
+
+        Ok(ProxyPool {
+            selection: config.selection,
+            eject_after_failures: config.eject_after_failures,
+            eject_for: Duration::from_secs(config.eject_secs),
+            next: Mutex::new(0),
+            proxies: Mutex::new(proxies),
+        })
+    }
+
+    /// The client for requests that aren't spread over the pool, like token
+    /// refreshes.
+    pub fn primary_client(&self) -> Client {
+        self.proxies.lock().expect("proxy pool lock poisoned")[0].client.clone()
+    }
+
+    pub fn pick(&self) -> ProxyLease {
+        let mut proxies = self.proxies.lock().expect("proxy pool lock poisoned");
+        let now = Instant::now();
+        for proxy in proxies.iter_mut() {
+            if proxy.ejected_until.is_some_and(|until| until <= now) {
+                info!("Proxy {} is back in the pool", proxy.label);
+                proxy.ejected_until = None;
+            }
+        }
+        PROXIES_AVAILABLE.set(proxies.iter().filter(|proxy| proxy.ejected_until.is_none()).count() as i64);
+
+        let available: Vec<usize> = (0..proxies.len()).filter(|&i| proxies[i].ejected_until.is_none()).collect();
+        let index = if available.is_empty() {
+            (0..proxies.len())
+                .min_by_key(|&i| proxies[i].ejected_until)
+                .expect("the pool always has a client")
+        } else {
+            match self.selection {
+                ProxySelection::RoundRobin => {
+                    let mut next = self.next.lock().expect("proxy pool lock poisoned");
+                    let index = available[*next % available.len()];
+                    *next = next.wrapping_add(1);
+                    index
+                }
+                // Never-used proxies sort first
+                ProxySelection::LeastRecentlyUsed => available
+                    .into_iter()
+                    .min_by_key(|&i| proxies[i].last_used)
+                    .expect("available is not empty"),
+            }
+        };
+
+        let proxy = &mut proxies[index];
+        proxy.last_used = Some(now);
+        ProxyLease { index, client: proxy.client.clone(), label: proxy.label.clone() }
+    }
+
+    /// Records whether the request through the proxy got an answer. Errors
+    /// the upstream API is responsible for should count as success here.
+    pub fn report(&self, lease: &ProxyLease, ok: bool) {
+        let mut proxies = self.proxies.lock().expect("proxy pool lock poisoned");
+        // Ejecting the only way out would change nothing
+        let single = proxies.len() == 1;
+        let proxy = &mut proxies[lease.index];
+        if ok {
+            proxy.failures = 0;
+            return;
+        }
+
+        proxy.failures += 1;
+        if !single && proxy.failures >= self.eject_after_failures && proxy.ejected_until.is_none() {
+            warn!(
+                "Proxy {} failed {} times in a row, ejecting it for {:?}",
+                proxy.label, proxy.failures, self.eject_for
+            );
+            proxy.failures = 0;
+            proxy.ejected_until = Some(Instant::now() + self.eject_for);
+            PROXY_EJECTIONS.with_label_values(&[proxy.label.as_str()]).inc();
+        }
+    }
+}
+
+impl ProxyState {
+    fn new(client: Client, label: String) -> Self {
+        ProxyState { client, label, last_used: None, failures: 0, ejected_until: None }
+    }
+}
+
+fn proxy_label(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(url) => format!(
+            "{}://{}:{}",
+            url.scheme(),
+            url.host_str().unwrap_or("?"),
+            url.port_or_known_default().map_or_else(|| "?".to_string(), |port| port.to_string())
+        ),
+        Err(_) => "proxy".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn pool(urls: &[&str], eject_secs: u64) -> ProxyPool {
+        let config = ProxyConfig {
+            urls: urls.iter().map(|&url| url.into()).collect(),
+            selection: ProxySelection::RoundRobin,
+            eject_after_failures: 2,
+            eject_secs,
+        };
+        ProxyPool::new(&config, &Config::for_tests().http).unwrap()
+    }
+
+    fn fail(pool: &ProxyPool, label: &str, times: usize) {
+        report(pool, label, false, times);
+    }
+
+    fn report(pool: &ProxyPool, label: &str, ok: bool, times: usize) {
+        for _ in 0..times {
+            let lease = loop {
+                let lease = pool.pick();
+                if lease.label == label {
+                    break lease;
+                }
+            };
+            pool.report(&lease, ok);
+        }
+    }
+
+    #[test]
+    fn ejects_a_proxy_after_failures_in_a_row() {
+        let pool = pool(&["http://a.test:8080", "http://b.test:8080"], 300);
+        fail(&pool, "http://a.test:8080", 1);
+        report(&pool, "http://a.test:8080", true, 1);
+        // A success in between resets the count
+        fail(&pool, "http://a.test:8080", 1);
+        assert!((0..4).any(|_| pool.pick().label == "http://a.test:8080"));
+
+        fail(&pool, "http://a.test:8080", 1);
+        assert!((0..4).all(|_| pool.pick().label == "http://b.test:8080"));
+    }
+
+    #[test]
+    fn an_ejected_proxy_comes_back_after_eject_secs() {
+        let pool = pool(&["http://a.test:8080", "http://b.test:8080"], 0);
+        fail(&pool, "http://a.test:8080", 2);
+        let labels: Vec<String> = (0..2).map(|_| pool.pick().label).collect();
+        assert!(labels.contains(&"http://a.test:8080".to_string()), "{labels:?}");
+    }
+
+    #[test]
+    fn uses_the_proxy_due_back_first_when_all_are_ejected() {
+        let pool = pool(&["http://a.test:8080", "http://b.test:8080"], 300);
+        fail(&pool, "http://b.test:8080", 2);
+        fail(&pool, "http://a.test:8080", 2);
+        assert!((0..3).all(|_| pool.pick().label == "http://b.test:8080"));
+    }
+
+    #[test]
+    fn never_ejects_the_only_client() {
+        let pool = pool(&[], 300);
+        for _ in 0..5 {
+            let lease = pool.pick();
+            assert_eq!(lease.label, "direct");
+            pool.report(&lease, false);
+        }
+    }
+}
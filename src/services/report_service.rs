@@ -0,0 +1,294 @@
+use async_channel::Sender;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime, Timelike, Utc, Weekday};
+use crate::config::ReportConfig;
+use crate::model::{AppState, JobRecord, Notification};
+use crate::utils::{humanize_job_type, next_weekly};
+use log::{error, info};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// Pay advertised for one location and job type.
+#[derive(Debug, Serialize)]
+pub struct PayStats {
+    pub location: String,
+    pub job_type: String,
+    /// Postings that advertised a maximum pay.
+    pub jobs: usize,
+    pub median_pay_max: f64,
+    pub max_pay_max: f64,
+}
+
+/// Posting and pay statistics over a window of the job history. Days and
+/// hours are local time, like `scheduler.hot_hours`.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub from: DateTime<Local>,
+    pub to: DateTime<Local>,
+    pub new_jobs: usize,
+    /// New postings per location per day.
+    pub postings: BTreeMap<String, BTreeMap<NaiveDate, usize>>,
+    /// Highest median first.
+    pub pay: Vec<PayStats>,
+    /// Jobs that closed within the window.
+    pub closed_jobs: usize,
+    pub avg_hours_to_close: Option<f64>,
+    /// New postings by local hour of day.
+    pub hourly: [usize; 24],
+}
+
+impl Report {
+    /// Covers the `days` days up to `to`.
+    pub fn build(records: &[JobRecord], to: DateTime<Local>, days: u32) -> Report {
+        let from = to - Duration::days(days as i64);
+        let in_window = |at: DateTime<Utc>| {
+            let at = at.with_timezone(&Local);
+            from <= at && at < to
+        };
+
+        let mut postings: BTreeMap<String, BTreeMap<NaiveDate, usize>> = BTreeMap::new();
+        let mut pay: BTreeMap<(String, String), Vec<f64>> = BTreeMap::new();
+        let mut hourly = [0; 24];
+        let mut new_jobs = 0;
+        for record in records.iter().filter(|record| in_window(record.first_seen)) {
+            let first_seen = record.first_seen.with_timezone(&Local);
+            let job = &record.job;
+            new_jobs += 1;
+            *postings
+                .entry(job.location.clone())
+                .or_default()
+                .entry(first_seen.date_naive())
+                .or_default() += 1;
+            hourly[first_seen.hour() as usize] += 1;
+            if let Some(pay_max) = job.pay_max {
+                pay.entry((job.location.clone(), humanize_job_type(&job.job_type)))
+                    .or_default()
+                    .push(pay_max);
+            }
+        }
+
+        let mut pay: Vec<PayStats> = pay
+            .into_iter()
+            .map(|((location, job_type), mut values)| {
+                values.sort_by(f64::total_cmp);
+                let middle = values.len() / 2;
+                let median = if values.len() % 2 == 0 {
+                    (values[middle - 1] + values[middle]) / 2.0
+                } else {
+                    values[middle]
+                };
+                PayStats {
+                    location,
+                    job_type,
+                    jobs: values.len(),
+                    median_pay_max: median,
+                    max_pay_max: values[values.len() - 1],
+                }
+            })
+            .collect();
+        pay.sort_by(|a, b| b.median_pay_max.total_cmp(&a.median_pay_max));
+
+        let hours_to_close: Vec<f64> = records
+            .iter()
+            .filter_map(|record| record.closed_at.filter(|&at| in_window(at)).map(|at| (at, record.first_seen)))
+            .map(|(closed_at, first_seen)| (closed_at - first_seen).num_seconds() as f64 / 3600.0)
+            .collect();
+        let avg_hours_to_close = (!hours_to_close.is_empty())
+            .then(|| hours_to_close.iter().sum::<f64>() / hours_to_close.len() as f64);
+
+        Report {
+            from,
+            to,
+            new_jobs,
+            postings,
+            pay,
+            closed_jobs: hours_to_close.len(),
+            avg_hours_to_close,
+            hourly,
+        }
+    }
+
+    /// Plain-text tables. `max_rows` caps the per-location sections so the
+    /// report fits in a chat message.
+    pub fn render(&self, max_rows: Option<usize>) -> String {
+        let max_rows = max_rows.unwrap_or(usize::MAX);
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "Job report {} to {}\n",
+            self.from.format("%Y-%m-%d %H:%M"),
+            self.to.format("%Y-%m-%d %H:%M")
+        );
+        let _ = writeln!(out, "New postings: {}", self.new_jobs);
+
+        // Busiest locations first, one column per day
+        let days: Vec<NaiveDate> = self
+            .from
+            .date_naive()
+            .iter_days()
+            .take_while(|day| *day <= self.to.date_naive())
+            .collect();
+        let mut locations: Vec<(&String, usize)> = self
+            .postings
+            .iter()
+            .map(|(location, per_day)| (location, per_day.values().sum()))
+            .collect();
+        locations.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        if !locations.is_empty() {
+            let _ = write!(out, "\nPostings per day\n{:<24}", "Location");
+            for day in &days {
+                let _ = write!(out, "{:>6}", day.format("%a"));
+            }
+            let _ = writeln!(out, "{:>7}", "Total");
+            for (location, total) in locations.iter().take(max_rows) {
+                let _ = write!(out, "{:<24}", truncate(location, 23));
+                for day in &days {
+                    let count = self.postings[*location].get(day).copied().unwrap_or(0);
+                    let _ = write!(out, "{:>6}", count);
+                }
+                let _ = writeln!(out, "{:>7}", total);
+            }
+            if locations.len() > max_rows {
+                let _ = writeln!(out, "... and {} more locations", locations.len() - max_rows);
+            }
+        }
+
+        if !self.pay.is_empty() {
+            let _ = writeln!(out, "\nAdvertised max pay by location and type");
+            let _ = writeln!(out, "{:<24}{:<16}{:>6}{:>9}{:>9}", "Location", "Type", "Jobs", "Median", "Max");
+            for stats in self.pay.iter().take(max_rows) {
+                let _ = writeln!(
+                    out,
+                    "{:<24}{:<16}{:>6}{:>9.2}{:>9.2}",
+                    truncate(&stats.location, 23),
+                    truncate(&stats.job_type, 15),
+                    stats.jobs,
+                    stats.median_pay_max,
+                    stats.max_pay_max
+                );
+            }
+            if self.pay.len() > max_rows {
+                let _ = writeln!(out, "... and {} more", self.pay.len() - max_rows);
+            }
+        }
+
+        let _ = writeln!(
+            out,
+            "\nClosed: {}, open for {} on average",
+            self.closed_jobs,
+            match self.avg_hours_to_close {
+                Some(hours) if hours >= 48.0 => format!("{:.1} days", hours / 24.0),
+                Some(hours) => format!("{:.1} hours", hours),
+                None => "-".to_string(),
+            }
+        );
+
+        let busiest = self.hourly.iter().copied().max().unwrap_or(0);
+        if busiest > 0 {
+            let _ = writeln!(out, "\nNew postings by hour");
+            for (hour, &count) in self.hourly.iter().enumerate() {
+                let bar = "#".repeat((count * 30).div_ceil(busiest));
+                let _ = writeln!(out, "{:02}:00 {:>5} {}", hour, count, bar);
+            }
+        }
+        out
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_chars - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+pub struct ReportService;
+
+impl ReportService {
+    /// Sends the report for the past `config.days` days every week at
+    /// `config.digest_day` and `config.digest_time`, local time.
+    pub async fn run_weekly(state: Arc<AppState>, config: ReportConfig, notifications: Sender<Notification>) {
+        let day: Weekday = config.digest_day.parse().expect("digest_day is validated on load");
+        let time = NaiveTime::parse_from_str(&config.digest_time, "%H:%M").expect("digest_time is validated on load");
+
+        loop {
+            let now = Local::now();
+            let next = next_weekly(now, day, time);
+            info!("Next weekly report at {}", next.format("%Y-%m-%d %H:%M"));
+            let wait = (next - now).to_std().unwrap_or_default();
+            tokio::time::sleep(wait).await;
+
+            let report = Report::build(&state.job_history(), Local::now(), config.days);
+            let text = report.render(Some(config.max_rows));
+            if let Err(e) = notifications.send(Notification::Report(text)).await {
+                error!("Failed to queue weekly report: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::JobInfo;
+    use chrono::TimeZone;
+
+    fn record(id: &str, location: &str, pay_max: Option<f64>, first_seen: DateTime<Local>) -> JobRecord {
+        let first_seen = first_seen.with_timezone(&Utc);
+        JobRecord {
+            job: JobInfo {
+                id: id.into(),
+                title: "Warehouse Associate".into(),
+                location: location.into(),
+                job_type: "FULL_TIME".into(),
+                pay_min: None,
+                pay_max,
+                shift: None,
+            },
+            profile: "default".into(),
+            first_seen,
+            last_seen: first_seen,
+            closed_at: None,
+        }
+    }
+
+    #[test]
+    fn build_counts_the_window_and_ranks_pay_by_median() {
+        let to = Local.with_ymd_and_hms(2024, 6, 10, 12, 0, 0).unwrap();
+        let mut opened_long_ago = record("J0", "Ottawa", Some(40.0), to - Duration::days(8));
+        opened_long_ago.closed_at = Some((to - Duration::days(1)).with_timezone(&Utc));
+        let mut closed_after_a_day = record("J1", "Toronto", Some(20.0), to - Duration::hours(30));
+        closed_after_a_day.closed_at = Some((to - Duration::hours(6)).with_timezone(&Utc));
+        let records = [
+            opened_long_ago,
+            closed_after_a_day,
+            record("J2", "Toronto", Some(22.0), to - Duration::hours(2)),
+            record("J3", "Toronto", Some(27.0), to - Duration::hours(3)),
+            record("J4", "Ottawa", Some(30.0), to - Duration::days(2)),
+            record("J5", "Ottawa", None, to - Duration::hours(1)),
+            record("J6", "Ottawa", Some(50.0), to + Duration::hours(1)),
+        ];
+
+        let report = Report::build(&records, to, 7);
+
+        assert_eq!(report.from, to - Duration::days(7));
+        assert_eq!(report.new_jobs, 5);
+        assert_eq!(report.postings["Toronto"].values().sum::<usize>(), 3);
+        assert_eq!(report.postings["Ottawa"].values().sum::<usize>(), 2);
+        assert_eq!(report.hourly.iter().sum::<usize>(), 5);
+
+        let pay: Vec<_> = report
+            .pay
+            .iter()
+            .map(|stats| (stats.location.as_str(), stats.job_type.as_str(), stats.jobs, stats.median_pay_max, stats.max_pay_max))
+            .collect();
+        assert_eq!(pay, [("Ottawa", "Full time", 1, 30.0, 30.0), ("Toronto", "Full time", 3, 22.0, 27.0)]);
+
+        // Closings count by when they closed, not when the job was first seen
+        assert_eq!(report.closed_jobs, 2);
+        assert_eq!(report.avg_hours_to_close, Some((7.0 * 24.0 + 24.0) / 2.0));
+    }
+}
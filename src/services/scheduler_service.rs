@@ -0,0 +1,241 @@
+use crate::config::Config;
+use crate::metrics::{HOT_HOURS_ACTIVE, POLL_INTERVAL_SECONDS, SCHEDULER_BACKOFFS};
+use crate::utils::{backoff_strategy, parse_time_range, time_in_range};
+use chrono::{Local, NaiveTime};
+use log::{info, warn};
+use rand::Rng;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub enum PollOutcome {
+    /// The poll returned something we had not seen before.
+    Changed,
+    Unchanged,
+    /// HTTP 429 or 5xx, optionally with the server's Retry-After.
+    Throttled(Option<Duration>),
+    Failed,
+}
+
+struct SchedulerState {
+    interval: Duration,
+    throttle_streak: u32,
+    backoff_until: Option<Instant>,
+    in_hot_hours: bool,
+}
+
+/// Decides how long to wait before the next poll. Polls are spaced evenly at
+/// `1s / requests_per_second`; the interval widens while results stay
+/// unchanged, snaps back on the first change or inside hot hours, and the
+/// whole monitor backs off with jitter when Amazon throttles us.
+pub struct Scheduler {
+    base_interval: Duration,
+    max_interval: Duration,
+    widen_factor: f64,
+    hot_hours: Vec<(NaiveTime, NaiveTime)>,
+    retry_base_ms: u64,
+    retry_max_delay_ms: u64,
+    state: Mutex<SchedulerState>,
+}
+
+impl Scheduler {
+    pub fn new(config: &Config) -> Self {
+        let base_interval =
+            Duration::from_millis(1000 / config.rate_limiting.requests_per_second.max(1) as u64);
+        POLL_INTERVAL_SECONDS.set(base_interval.as_secs_f64());
+
+        Scheduler {
+            base_interval,
+            max_interval: Duration::from_millis(config.scheduler.max_interval_ms).max(base_interval),
+            widen_factor: config.scheduler.widen_factor,
+            hot_hours: config
+                .scheduler
+                .hot_hours
+                .iter()
+                .filter_map(|range| parse_time_range(range))
+                .collect(),
+            retry_base_ms: config.rate_limiting.retry_base_ms,
+            retry_max_delay_ms: config.rate_limiting.retry_max_delay_ms,
+            state: Mutex::new(SchedulerState {
+                interval: base_interval,
+                throttle_streak: 0,
+                backoff_until: None,
+                in_hot_hours: false,
+            }),
+        }
+    }
+
+    pub fn next_delay(&self) -> Duration {
+        let mut state = self.state.lock().expect("scheduler lock poisoned");
+
+        let in_hot_hours = self.in_hot_hours(Local::now().time());
+        if in_hot_hours != state.in_hot_hours {
+            state.in_hot_hours = in_hot_hours;
+            HOT_HOURS_ACTIVE.set(in_hot_hours as i64);
+            if in_hot_hours {
+                info!("Entering hot hours, polling every {:?}", self.base_interval);
+                self.set_interval(&mut state, self.base_interval);
+            } else {
+                info!("Leaving hot hours");
+            }
+        }
+
+        match state.backoff_until {
+            Some(until) if until > Instant::now() => {
+                state.interval.max(until.saturating_duration_since(Instant::now()))
+            }
+            _ => state.interval,
+        }
+    }
+
+    pub fn record(&self, outcome: PollOutcome) {
+        let mut state = self.state.lock().expect("scheduler lock poisoned");
+
+        match outcome {
+            PollOutcome::Changed => {
+                state.throttle_streak = 0;
+                if state.interval != self.base_interval {
+                    info!("Results changed, polling every {:?} again", self.base_interval);
+                    self.set_interval(&mut state, self.base_interval);
+                }
+            }
+            PollOutcome::Unchanged => {
+                state.throttle_streak = 0;
+                if state.in_hot_hours || state.interval >= self.max_interval {
+                    return;
+                }
+                let widened = state.interval.mul_f64(self.widen_factor).min(self.max_interval);
+                if widened != state.interval {
+                    info!("No changes, widening poll interval to {:?}", widened);
+                    self.set_interval(&mut state, widened);
+                }
+            }
+            PollOutcome::Throttled(retry_after) => {
+                let delay = backoff_strategy(
+                    state.throttle_streak,
+                    self.retry_base_ms,
+                    self.retry_max_delay_ms,
+                );
+                let jitter = Duration::from_millis(
+                    rand::thread_rng().gen_range(0..=delay.as_millis() as u64 / 2),
+                );
+                let delay = (delay + jitter).max(retry_after.unwrap_or_default());
+                state.throttle_streak = state.throttle_streak.saturating_add(1);
+
+                let until = Instant::now() + delay;
+                if state.backoff_until.is_none_or(|current| current < until) {
+                    state.backoff_until = Some(until);
+                    SCHEDULER_BACKOFFS.inc();
+                    warn!(
+                        "Throttled by Amazon ({} in a row), pausing all polls for {:?}",
+                        state.throttle_streak, delay
+                    );
+                }
+            }
+            PollOutcome::Failed => {}
+        }
+    }
+
+    fn set_interval(&self, state: &mut SchedulerState, interval: Duration) {
+        state.interval = interval;
+        POLL_INTERVAL_SECONDS.set(interval.as_secs_f64());
+    }
+
+    fn in_hot_hours(&self, now: NaiveTime) -> bool {
+        self.hot_hours.iter().any(|&range| time_in_range(now, range))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Polls every 500ms, widening by half up to 1s.
+    fn scheduler() -> Scheduler {
+        let mut config = Config::for_tests();
+        config.rate_limiting.requests_per_second = 2;
+        config.rate_limiting.retry_base_ms = 500;
+        config.rate_limiting.retry_max_delay_ms = 10_000;
+        config.scheduler.max_interval_ms = 1000;
+        config.scheduler.widen_factor = 1.5;
+        config.scheduler.hot_hours = vec!["22:00-02:00".into()];
+        Scheduler::new(&config)
+    }
+
+    fn interval(scheduler: &Scheduler) -> Duration {
+        scheduler.state.lock().unwrap().interval
+    }
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn unchanged_results_widen_up_to_the_maximum() {
+        let scheduler = scheduler();
+        assert_eq!(interval(&scheduler), millis(500));
+        scheduler.record(PollOutcome::Unchanged);
+        assert_eq!(interval(&scheduler), millis(750));
+        scheduler.record(PollOutcome::Unchanged);
+        assert_eq!(interval(&scheduler), millis(1000));
+        scheduler.record(PollOutcome::Unchanged);
+        assert_eq!(interval(&scheduler), millis(1000));
+        scheduler.record(PollOutcome::Failed);
+        assert_eq!(interval(&scheduler), millis(1000));
+    }
+
+    #[test]
+    fn a_change_snaps_back_to_the_base_interval() {
+        let scheduler = scheduler();
+        scheduler.record(PollOutcome::Unchanged);
+        scheduler.record(PollOutcome::Changed);
+        assert_eq!(interval(&scheduler), millis(500));
+    }
+
+    #[test]
+    fn hot_hours_pin_the_base_interval() {
+        let scheduler = scheduler();
+        scheduler.state.lock().unwrap().in_hot_hours = true;
+        scheduler.record(PollOutcome::Unchanged);
+        assert_eq!(interval(&scheduler), millis(500));
+        assert!(scheduler.in_hot_hours(NaiveTime::from_hms_opt(23, 0, 0).unwrap()));
+        assert!(scheduler.in_hot_hours(NaiveTime::from_hms_opt(1, 59, 0).unwrap()));
+        assert!(!scheduler.in_hot_hours(NaiveTime::from_hms_opt(2, 0, 0).unwrap()));
+        assert!(!scheduler.in_hot_hours(NaiveTime::from_hms_opt(12, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn throttling_backs_off_exponentially_with_bounded_jitter() {
+        let scheduler = scheduler();
+        scheduler.state.lock().unwrap().in_hot_hours = scheduler.in_hot_hours(Local::now().time());
+
+        // 500ms plus up to half again
+        scheduler.record(PollOutcome::Throttled(None));
+        let delay = scheduler.next_delay();
+        assert!(delay > millis(400) && delay <= millis(750), "{:?}", delay);
+
+        // 1000ms plus up to half again
+        scheduler.record(PollOutcome::Throttled(None));
+        let delay = scheduler.next_delay();
+        assert!(delay > millis(900) && delay <= millis(1500), "{:?}", delay);
+        assert_eq!(scheduler.state.lock().unwrap().throttle_streak, 2);
+
+        scheduler.record(PollOutcome::Unchanged);
+        assert_eq!(scheduler.state.lock().unwrap().throttle_streak, 0);
+    }
+
+    #[test]
+    fn retry_after_is_honoured_when_longer() {
+        let scheduler = scheduler();
+        scheduler.record(PollOutcome::Throttled(Some(Duration::from_secs(5))));
+        let delay = scheduler.next_delay();
+        assert!(delay > millis(4900) && delay <= millis(5000), "{:?}", delay);
+    }
+
+    #[test]
+    fn an_expired_backoff_leaves_the_interval() {
+        let scheduler = scheduler();
+        scheduler.state.lock().unwrap().in_hot_hours = scheduler.in_hot_hours(Local::now().time());
+        scheduler.state.lock().unwrap().backoff_until = Some(Instant::now());
+        assert_eq!(scheduler.next_delay(), millis(500));
+    }
+}
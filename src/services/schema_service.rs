@@ -0,0 +1,277 @@
+use anyhow::{Context, Result};
+use async_channel::Sender;
+use crate::model::Notification;
+use chrono::Local;
+use log::{error, info, warn};
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs::{self, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const KNOWN_SHAPES_FILE: &str = "known_shapes.txt";
+
+struct KnownShapes {
+    /// Each shape's fields joined with `|`, as stored on disk.
+    seen: HashSet<String>,
+    latest: Option<BTreeSet<String>>,
+}
+
+/// Watches the shape of Amazon's responses. The first time a shape shows up
+/// the raw response is written to `schema.capture_dir`, and when it differs
+/// from what we have seen before the operator gets a one-time alert.
+pub struct SchemaService {
+    capture_dir: PathBuf,
+    known: Mutex<KnownShapes>,
+    alerts: Sender<Notification>,
+}
+
+impl SchemaService {
+    pub fn new(capture_dir: &str, alerts: Sender<Notification>) -> Self {
+        let capture_dir = PathBuf::from(capture_dir);
+        let mut known = KnownShapes { seen: HashSet::new(), latest: None };
+
+        // One shape per line, oldest first
+        if let Ok(contents) = fs::read_to_string(capture_dir.join(KNOWN_SHAPES_FILE)) {
+            for line in contents.lines().filter(|line| !line.is_empty()) {
+                known.seen.insert(line.to_string());
+                known.latest = Some(line.split('|').map(str::to_string).collect());
+            }
+        }
+
+        SchemaService {
+            capture_dir,
+            known: Mutex::new(known),
+            alerts,
+        }
+    }
+
+    pub async fn observe(&self, profile: &str, body: &str, page: &Value) {
+        let Some(shape) = shape_of(page) else {
+            return;
+        };
+        let key = shape.iter().map(String::as_str).collect::<Vec<_>>().join("|");
+
+        let previous = {
+            let mut known = self.known.lock().expect("schema lock poisoned");
+            if !known.seen.insert(key.clone()) {
+                return;
+            }
+            known.latest.replace(shape.clone())
+        };
+
+        match self.capture(&key, body) {
+            Ok(path) => info!("Captured new response shape from profile {} to {}", profile, path.display()),
+            Err(e) => warn!("Failed to capture new response shape: {:#}", e),
+        }
+
+        // The very first shape is the baseline, not a change
+        let Some(previous) = previous else {
+            return;
+        };
+        let added: Vec<&str> = shape.difference(&previous).map(String::as_str).collect();
+        let removed: Vec<&str> = previous.difference(&shape).map(String::as_str).collect();
+        warn!(
+            "Amazon response shape changed (profile {}). Added: [{}]. Removed: [{}]",
+            profile,
+            added.join(", "),
+            removed.join(", ")
+        );
+
+        let alert = format!(
+            "Amazon changed the job search response shape (profile {}).\nAdded: {}\nRemoved: {}\nRaw response saved in {}",
+            profile,
+            if added.is_empty() { "-".to_string() } else { added.join(", ") },
+            if removed.is_empty() { "-".to_string() } else { removed.join(", ") },
+            self.capture_dir.display()
+        );
+        if let Err(e) = self.alerts.try_send(Notification::Alert(alert)) {
+            error!("Failed to queue schema alert: {}", e);
+        }
+    }
+
+    fn capture(&self, key: &str, body: &str) -> Result<PathBuf> {
+        fs::create_dir_all(&self.capture_dir)
+            .with_context(|| format!("Failed to create {}", self.capture_dir.display()))?;
+
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let path = self.capture_dir.join(format!(
+            "{}-{:016x}.json",
+            Local::now().format("%Y%m%d-%H%M%S"),
+            hasher.finish()
+        ));
+        fs::write(&path, body)?;
+
+        let mut known_file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.capture_dir.join(KNOWN_SHAPES_FILE))?;
+        writeln!(known_file, "{}", key)?;
+        Ok(path)
+    }
+}
+
+/// Describes a response as the set of object key paths down to the job
+/// cards, plus each card field with the JSON types it carried across the
+/// page (`null` only when every card had it null). Returns `None` for pages
+/// without cards since they say nothing about the card layout.
+fn shape_of(page: &Value) -> Option<BTreeSet<String>> {
+    let mut shape = BTreeSet::new();
+    let mut current = page;
+    let mut path = String::new();
+    for key in ["data", "searchJobCardsByLocation"] {
+        let Some(object) = current.as_object() else {
+            break;
+        };
+        for name in object.keys() {
+            shape.insert(format!("{}{}", path, name));
+        }
+        path = format!("{}{}.", path, key);
+        match object.get(key) {
+            Some(next) => current = next,
+            None => return Some(shape),
+        }
+    }
+    if let Some(object) = current.as_object() {
+        for name in object.keys() {
+            shape.insert(format!("{}{}", path, name));
+        }
+    }
+
+    let cards = current.get("jobCards").and_then(Value::as_array)?;
+    if cards.is_empty() {
+        return None;
+    }
+
+    let mut fields: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for card in cards {
+        let Some(card) = card.as_object() else {
+            fields.entry("<non-object card>").or_default();
+            continue;
+        };
+        for (name, value) in card {
+            let types = fields.entry(name.as_str()).or_default();
+            match value {
+                Value::Null => {}
+                Value::Bool(_) => {
+                    types.insert("bool");
+                }
+                Value::Number(_) => {
+                    types.insert("number");
+                }
+                Value::String(_) => {
+                    types.insert("string");
+                }
+                Value::Array(_) => {
+                    types.insert("array");
+                }
+                Value::Object(_) => {
+                    types.insert("object");
+                }
+            }
+        }
+    }
+
+    for (name, types) in fields {
+        let types = if types.is_empty() {
+            "null".to_string()
+        } else {
+            types.into_iter().collect::<Vec<_>>().join("/")
+        };
+        shape.insert(format!("{}jobCards[].{}: {}", path, name, types));
+    }
+    Some(shape)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_channel::bounded;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn page(cards: Value) -> Value {
+        json!({ "data": { "searchJobCardsByLocation": { "nextToken": null, "jobCards": cards } } })
+    }
+
+    fn shape(page: &Value) -> Vec<String> {
+        shape_of(page).expect("page has a shape").into_iter().collect()
+    }
+
+    #[test]
+    fn cards_contribute_their_fields_and_types() {
+        let page = page(json!([
+            { "jobId": "J1", "totalPayRateMin": 18.5, "scheduleCount": null },
+            { "jobId": 2, "totalPayRateMin": null, "scheduleCount": null },
+        ]));
+        assert_eq!(
+            shape(&page),
+            [
+                "data",
+                "data.searchJobCardsByLocation",
+                "data.searchJobCardsByLocation.jobCards",
+                "data.searchJobCardsByLocation.jobCards[].jobId: number/string",
+                "data.searchJobCardsByLocation.jobCards[].scheduleCount: null",
+                "data.searchJobCardsByLocation.jobCards[].totalPayRateMin: number",
+                "data.searchJobCardsByLocation.nextToken",
+            ]
+        );
+    }
+
+    #[test]
+    fn a_field_is_null_only_when_every_card_has_it_null() {
+        let all_null = page(json!([{ "jobId": null }, { "jobId": null }]));
+        let one_set = page(json!([{ "jobId": null }, { "jobId": "J2" }]));
+        assert!(shape(&all_null).contains(&"data.searchJobCardsByLocation.jobCards[].jobId: null".to_string()));
+        assert!(shape(&one_set).contains(&"data.searchJobCardsByLocation.jobCards[].jobId: string".to_string()));
+    }
+
+    #[test]
+    fn pages_without_cards_have_no_shape() {
+        assert_eq!(shape_of(&page(json!([]))), None);
+        assert_eq!(shape_of(&json!({ "data": { "searchJobCardsByLocation": {} } })), None);
+    }
+
+    #[test]
+    fn a_missing_level_ends_the_shape_there() {
+        assert_eq!(shape(&json!({ "errors": [{ "message": "boom" }] })), ["errors"]);
+    }
+
+    #[test]
+    fn non_object_cards_are_marked() {
+        let page = page(json!([{ "jobId": "J1" }, "J2"]));
+        assert!(shape(&page).contains(&"data.searchJobCardsByLocation.jobCards[].<non-object card>: null".to_string()));
+    }
+
+    #[tokio::test]
+    async fn only_a_new_shape_after_the_first_alerts() {
+        let dir = TempDir::new().unwrap();
+        let (alerts, received) = bounded(10);
+        let service = SchemaService::new(dir.path().to_str().unwrap(), alerts);
+        let first = page(json!([{ "jobId": "J1" }]));
+        let second = page(json!([{ "jobId": "J1", "jobTitle": "Sorter" }]));
+
+        service.observe("default", "{}", &first).await;
+        service.observe("default", "{}", &first).await;
+        assert!(received.is_empty());
+        service.observe("default", "{}", &second).await;
+        service.observe("default", "{}", &second).await;
+        assert_eq!(received.len(), 1);
+        let Ok(Notification::Alert(alert)) = received.try_recv() else {
+            panic!("expected an alert");
+        };
+        assert!(alert.contains("Added: data.searchJobCardsByLocation.jobCards[].jobTitle: string"), "{}", alert);
+        assert!(alert.contains("Removed: -"), "{}", alert);
+
+        // Known shapes survive a restart
+        let (alerts, received) = bounded(10);
+        let service = SchemaService::new(dir.path().to_str().unwrap(), alerts);
+        service.observe("default", "{}", &second).await;
+        service.observe("default", "{}", &first).await;
+        assert!(received.is_empty());
+    }
+}
@@ -33,14 +33,30 @@ impl ShutdownService {
         self.handle.clone()
     }
 
+    /// Stops the supervised tasks without a signal, e.g. when the monitor died.
+    pub fn shut_down(&self) {
+        self.handle.flag.store(true, Ordering::Relaxed);
+    }
+
+    /// Waits for Ctrl-C, or SIGTERM from `docker stop`, systemd or
+    /// Kubernetes.
     pub async fn wait_for_shutdown(&self) -> anyhow::Result<()> {
-        match signal::ctrl_c().await {
-            Ok(()) => {
+        #[cfg(unix)]
+        let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())
+            .map_err(|err| anyhow::anyhow!("Unable to listen for SIGTERM: {}", err))?;
+        #[cfg(unix)]
+        let terminated = terminate.recv();
+        #[cfg(not(unix))]
+        let terminated = std::future::pending::<Option<()>>();
+
+        tokio::select! {
+            result = signal::ctrl_c() => {
+                result.map_err(|err| anyhow::anyhow!("Unable to listen for shutdown signal: {}", err))?;
                 info!("Shutdown signal received");
-                self.handle.flag.store(true, Ordering::Relaxed);
-                Ok(())
             }
-            Err(err) => Err(anyhow::anyhow!("Unable to listen for shutdown signal: {}", err)),
+            _ = terminated => info!("SIGTERM received"),
         }
+        self.handle.flag.store(true, Ordering::Relaxed);
+        Ok(())
     }
-}
\ No newline at end of file
+}
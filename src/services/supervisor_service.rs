@@ -0,0 +1,138 @@
+use async_channel::Sender;
+use crate::config::SupervisorConfig;
+use crate::metrics::TASK_RESTARTS;
+use crate::model::{AppState, Notification};
+use crate::services::shutdown_service::ShutdownHandle;
+use crate::utils::backoff_strategy;
+use log::{error, info, warn};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// Marks a task as stopped when dropped, which also covers panics.
+struct RunningGuard {
+    state: Arc<AppState>,
+    name: &'static str,
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.state.health.set_task_running(self.name, false);
+    }
+}
+
+/// Spawns the long-running background tasks and keeps track of which of
+/// them are still alive for `/healthz`. Tasks started with
+/// [`Supervisor::supervise`] are restarted with backoff whenever they panic
+/// or return, and the operator is alerted when one keeps flapping.
+#[derive(Clone)]
+pub struct Supervisor {
+    state: Arc<AppState>,
+    config: SupervisorConfig,
+    alerts: Sender<Notification>,
+    shutdown_handle: ShutdownHandle,
+}
+
+impl Supervisor {
+    pub fn new(
+        state: Arc<AppState>,
+        config: SupervisorConfig,
+        alerts: Sender<Notification>,
+        shutdown_handle: ShutdownHandle,
+    ) -> Self {
+        Supervisor { state, config, alerts, shutdown_handle }
+    }
+
+    /// Spawns a task that is tracked but never restarted.
+    pub fn spawn<F>(&self, name: &'static str, task: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.state.health.set_task_running(name, true);
+        let guard = RunningGuard {
+            state: self.state.clone(),
+            name,
+        };
+        tokio::spawn(async move {
+            let _guard = guard;
+            task.await
+        })
+    }
+
+    /// Spawns the task built by `factory` and builds a fresh one each time
+    /// the previous one ends, until shutdown. The handle finishes once the
+    /// last run has.
+    pub fn supervise<F, Fut>(&self, name: &'static str, factory: F) -> JoinHandle<()>
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let supervisor = self.clone();
+        tokio::spawn(async move {
+            let window = Duration::from_secs(supervisor.config.flap_window_secs);
+            let mut recent_restarts: VecDeque<Instant> = VecDeque::new();
+            let mut flap_alerted = false;
+            let mut attempt = 0;
+
+            loop {
+                let started = Instant::now();
+                let result = supervisor.spawn(name, factory()).await;
+                if supervisor.shutdown_handle.is_shutdown() {
+                    break;
+                }
+
+                match result {
+                    Ok(()) => warn!("Task {} exited unexpectedly", name),
+                    Err(e) => error!("Task {} crashed: {}", name, e),
+                }
+                TASK_RESTARTS.with_label_values(&[name]).inc();
+                supervisor.state.health.record_task_restart(name);
+
+                // A task that ran for a full window was healthy; start the
+                // backoff over
+                if started.elapsed() >= window {
+                    attempt = 0;
+                }
+                let now = Instant::now();
+                recent_restarts.push_back(now);
+                while recent_restarts.front().is_some_and(|&at| now.duration_since(at) > window) {
+                    recent_restarts.pop_front();
+                }
+
+                if recent_restarts.len() >= supervisor.config.flap_threshold {
+                    if !flap_alerted {
+                        flap_alerted = true;
+                        supervisor.alert_flapping(name, recent_restarts.len());
+                    }
+                } else {
+                    flap_alerted = false;
+                }
+
+                let delay = backoff_strategy(
+                    attempt,
+                    supervisor.config.restart_base_ms,
+                    supervisor.config.restart_max_delay_ms,
+                );
+                attempt = attempt.saturating_add(1);
+                info!("Restarting task {} in {:?}", name, delay);
+                tokio::time::sleep(delay).await;
+            }
+        })
+    }
+
+    fn alert_flapping(&self, name: &str, restarts: usize) {
+        let text = format!(
+            "Background task `{}` restarted {} times in the last {}s and keeps failing.",
+            name, restarts, self.config.flap_window_secs
+        );
+        error!("{}", text);
+        // Never wait for room: the flapping task may be the notification
+        // worker itself, and then nothing would drain the queue
+        if let Err(e) = self.alerts.try_send(Notification::Alert(text)) {
+            error!("Failed to queue supervisor alert: {}", e);
+        }
+    }
+}
@@ -1,8 +1,47 @@
 use anyhow::Result;
 use crate::config::Config;
-use crate::model::{NotificationBatch};
-use crate::utils::{escape_html, humanize_job_type};
-use reqwest::Client;
+use crate::model::{JobRecord, NotificationBatch};
+use crate::services::http_service::client_builder;
+use crate::utils::{escape_html, format_pay, humanize_job_type};
+use reqwest::{Client, StatusCode};
+use std::fmt;
+
+/// Telegram allows 4096 characters, keep some room for markup.
+const MAX_MESSAGE_LEN: usize = 4000;
+
+/// Telegram answered but refused this one message, e.g. bad markup, a chat
+/// that blocked the bot or a rate limit. Says nothing about whether Telegram
+/// is reachable.
+#[derive(Debug)]
+pub struct Rejected {
+    status: StatusCode,
+    text: String,
+}
+
+impl Rejected {
+    /// Sending the same message again won't help, unlike after a rate limit.
+    pub fn is_final(&self) -> bool {
+        self.status != StatusCode::TOO_MANY_REQUESTS
+    }
+}
+
+impl fmt::Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Telegram rejected the message: {}", self.text)
+    }
+}
+
+impl std::error::Error for Rejected {}
+
+/// How loudly a message arrives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    /// Delivered without a notification sound, for quiet hours.
+    Silent,
+    Normal,
+    /// Flagged as a priority job and never silenced.
+    Priority,
+}
 
 #[derive(Clone)]
 pub struct TelegramService {
@@ -12,44 +51,196 @@ pub struct TelegramService {
 
 impl TelegramService {
     pub fn new(config: Config) -> Self {
-        TelegramService {
-            client: Client::new(),
-            config,
-        }
+        let client = client_builder(&config.http)
+            .and_then(|builder| Ok(builder.build()?))
+            .expect("Failed to create HTTP client");
+        TelegramService { client, config }
     }
 
-    pub async fn send_batch(&self, batch: &NotificationBatch) -> Result<()> {
-        let mut message = format!(
-            "<b>New Jobs in {}</b>\n═══════════════════\n",
+    /// Sends the jobs in as many messages as Telegram's size limit needs.
+    /// Returns the IDs of the jobs in the messages that went out.
+    pub async fn send_batch<'b>(
+        &self,
+        chat_id: &str,
+        batch: &'b NotificationBatch,
+        urgency: Urgency,
+    ) -> (Vec<&'b str>, Result<()>) {
+        let header = format!(
+            "{}<b>New Jobs in {}</b>\n═══════════════════\n",
+            if urgency == Urgency::Priority { "🚨 <b>Priority</b> " } else { "" },
             escape_html(&batch.location)
         );
 
-        for job in &batch.jobs {
-            let job_type = humanize_job_type(&job.job_type);
-            message.push_str(&format!(
-                "<b>{}</b>\n- Type: {}\n- Shifts: {}\n- Pay: ${:.2}-${:.2}/hr\n═══════════════════\n",
-                escape_html(&job.title),
-                job_type,
-                job.shift,
-                job.pay_min,
-                job.pay_max
-            ));
+        let lines = batch
+            .jobs
+            .iter()
+            .map(|job| {
+                let line = format!(
+                    "<b>{}</b>\n- Type: {}\n- Shifts: {}\n- Pay: {}\n═══════════════════",
+                    escape_html(&job.title),
+                    humanize_job_type(&job.job_type),
+                    job.shift.map_or_else(|| "?".to_string(), |shift| shift.to_string()),
+                    format_pay(job.pay_min, job.pay_max)
+                );
+                (line, Some(job.id.as_str()))
+            })
+            .collect();
+
+        self.send_split(chat_id, &header, lines, urgency).await
+    }
+
+    /// Sends the collected jobs grouped by location, best paid first, split
+    /// over as many messages as Telegram's size limit needs. Returns the IDs
+    /// of the jobs in the messages that went out, which on a failure are
+    /// the ones that don't need sending again.
+    pub async fn send_digest<'r>(
+        &self,
+        chat_id: &str,
+        records: &'r [JobRecord],
+        urgency: Urgency,
+    ) -> (Vec<&'r str>, Result<()>) {
+        let mut by_location: Vec<(&str, Vec<&JobRecord>)> = Vec::new();
+        for record in records {
+            match by_location.iter_mut().find(|(location, _)| *location == record.job.location) {
+                Some((_, jobs)) => jobs.push(record),
+                None => by_location.push((&record.job.location, vec![record])),
+            }
+        }
+        let best_pay = |record: &JobRecord| record.job.pay_max.or(record.job.pay_min).unwrap_or(0.0);
+        for (_, jobs) in &mut by_location {
+            jobs.sort_by(|a, b| best_pay(b).total_cmp(&best_pay(a)));
+        }
+        by_location.sort_by(|(_, a), (_, b)| best_pay(b[0]).total_cmp(&best_pay(a[0])));
+
+        let closed = records.iter().filter(|record| record.closed_at.is_some()).count();
+        let header = format!(
+            "🗞 <b>Job digest</b>: {} jobs{}\n",
+            records.len(),
+            if closed > 0 { format!(", {} already closed", closed) } else { String::new() }
+        );
+        let mut lines = Vec::new();
+        for (location, jobs) in by_location {
+            lines.push((format!("\n<b>{}</b>", escape_html(location)), None));
+            for record in jobs {
+                let job = &record.job;
+                let title = if record.closed_at.is_some() {
+                    format!("<s>{}</s> (closed)", escape_html(&job.title))
+                } else {
+                    escape_html(&job.title)
+                };
+                let line = format!(
+                    "• {} - {} - {}",
+                    title,
+                    humanize_job_type(&job.job_type),
+                    format_pay(job.pay_min, job.pay_max)
+                );
+                lines.push((line, Some(job.id.as_str())));
+            }
+        }
+
+        self.send_split(chat_id, &header, lines, urgency).await
+    }
+
+    /// Sends the lines in order, starting a new message that repeats
+    /// `header` whenever the next line would push one past the size limit.
+    /// Stops at the first message that fails, and returns the job IDs of the
+    /// lines in the messages that went out.
+    async fn send_split<'j>(
+        &self,
+        chat_id: &str,
+        header: &str,
+        lines: Vec<(String, Option<&'j str>)>,
+        urgency: Urgency,
+    ) -> (Vec<&'j str>, Result<()>) {
+        let mut sent = Vec::new();
+        let mut message = header.to_string();
+        let mut in_message = Vec::new();
+        for (line, job_id) in lines {
+            if message.len() > header.len() && message.len() + line.len() + 1 > MAX_MESSAGE_LEN {
+                if let Err(e) = self.send_message(chat_id, &message, urgency).await {
+                    return (sent, Err(e));
+                }
+                sent.append(&mut in_message);
+                message.truncate(header.len());
+            }
+            message.push_str(&line);
+            message.push('\n');
+            in_message.extend(job_id);
+        }
+        let result = self.send_message(chat_id, &message, urgency).await;
+        if result.is_ok() {
+            sent.append(&mut in_message);
+        }
+        (sent, result)
+    }
+
+    pub async fn send_operator_alert(&self, text: &str) -> Result<()> {
+        let message = format!("⚠️ <b>Job monitor alert</b>\n{}", escape_html(text));
+        self.send_message(&self.config.telegram.chat_id, &message, Urgency::Normal).await
+    }
+
+    pub async fn send_report(&self, report: &str) -> Result<()> {
+        // Escaping can grow the text several times over, so cut afterwards
+        let mut text = escape_html(report);
+        let room = MAX_MESSAGE_LEN - 100;
+        if let Some((cut, _)) = text.char_indices().nth(room) {
+            text.truncate(cut);
+            // Don't leave half an entity like `&am`
+            if let Some(amp) = text.rfind('&')
+                && !text[amp..].contains(';')
+            {
+                text.truncate(amp);
+            }
+            text.push_str("\n…");
+        }
+        let message = format!("📊 <b>Weekly job report</b>\n<pre>{}</pre>", text);
+        self.send_message(&self.config.telegram.chat_id, &message, Urgency::Normal).await
+    }
+
+    /// Calls `getMe`, which succeeds whenever the API is up and the token valid.
+    pub async fn check_reachable(&self) -> Result<()> {
+        self.try_check_reachable()
+            .await
+            .map_err(|e| anyhow::anyhow!(self.config.redact(&format!("{:#}", e))))
+    }
+
+    async fn try_check_reachable(&self) -> Result<()> {
+        let url = format!(
+            "{}/bot{}/getMe",
+            self.config.telegram.api_url.trim_end_matches('/'),
+            self.config.telegram.bot_token.expose()
+        );
+
+        let response = self.client.get(&url).send().await?;
+        if !response.status().is_success() {
+            let text = response.text().await?;
+            return Err(anyhow::anyhow!("Telegram API error: {}", text));
         }
 
-        self.send_alert(&message).await
+        Ok(())
+    }
+
+    async fn send_message(&self, chat_id: &str, message: &str, urgency: Urgency) -> Result<()> {
+        // The request URL carries the bot token, so scrub it from any error
+        self.try_send_message(chat_id, message, urgency).await.map_err(|e| match e.downcast::<Rejected>() {
+            Ok(Rejected { status, text }) => Rejected { status, text: self.config.redact(&text) }.into(),
+            Err(e) => anyhow::anyhow!(self.config.redact(&format!("{:#}", e))),
+        })
     }
 
-    async fn send_alert(&self, message: &str) -> Result<()> {
+    async fn try_send_message(&self, chat_id: &str, message: &str, urgency: Urgency) -> Result<()> {
         let url = format!(
-            "https://api.telegram.org/bot{}/sendMessage",
-            self.config.telegram.bot_token
+            "{}/bot{}/sendMessage",
+            self.config.telegram.api_url.trim_end_matches('/'),
+            self.config.telegram.bot_token.expose()
         );
 
         let payload = serde_json::json!({
-            "chat_id": self.config.telegram.chat_id,
+            "chat_id": chat_id,
             "text": message,
             "parse_mode": "HTML",
-            "disable_web_page_preview": true
+            "disable_web_page_preview": true,
+            "disable_notification": urgency == Urgency::Silent
         });
 
         let response = self.client
@@ -58,11 +249,147 @@ impl TelegramService {
             .send()
             .await?;
 
-        if !response.status().is_success() {
+        let status = response.status();
+        if !status.is_success() {
             let text = response.text().await?;
+            // A bad token or API URL fails every message, not just this one
+            if status.is_client_error() && status != StatusCode::UNAUTHORIZED && status != StatusCode::NOT_FOUND {
+                return Err(Rejected { status, text }.into());
+            }
             return Err(anyhow::anyhow!("Telegram API error: {}", text));
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::JobInfo;
+    use axum::extract::State;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Chat {
+        messages: Vec<String>,
+        /// Answers every message from this one on with the status.
+        fail_from: Option<(usize, u16)>,
+    }
+
+    async fn send_message(
+        State(chat): State<Arc<Mutex<Chat>>>,
+        Json(payload): Json<serde_json::Value>,
+    ) -> (axum::http::StatusCode, Json<serde_json::Value>) {
+        let mut chat = chat.lock().unwrap();
+        if let Some((from, status)) = chat.fail_from
+            && chat.messages.len() >= from
+        {
+            let status = axum::http::StatusCode::from_u16(status).unwrap();
+            return (status, Json(serde_json::json!({ "ok": false })));
+        }
+        chat.messages.push(payload["text"].as_str().unwrap().to_string());
+        (axum::http::StatusCode::OK, Json(serde_json::json!({ "ok": true })))
+    }
+
+    async fn service(fail_from: Option<(usize, u16)>) -> (TelegramService, Arc<Mutex<Chat>>) {
+        let chat = Arc::new(Mutex::new(Chat { fail_from, ..Chat::default() }));
+        let router = Router::new().route("/{bot}/sendMessage", post(send_message)).with_state(chat.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut config = Config::for_tests();
+        config.telegram.api_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        (TelegramService::new(config), chat)
+    }
+
+    fn records(count: usize) -> Vec<JobRecord> {
+        let at = chrono::Utc::now();
+        (0..count)
+            .map(|n| JobRecord {
+                job: JobInfo {
+                    id: format!("J{:03}", n),
+                    title: format!("Fulfillment Center Warehouse Associate J{:03}", n),
+                    location: "Brampton, ON".into(),
+                    job_type: "FULL_TIME".into(),
+                    pay_min: Some(19.5),
+                    pay_max: Some(21.0),
+                    shift: None,
+                },
+                profile: "default".into(),
+                first_seen: at,
+                last_seen: at,
+                closed_at: None,
+            })
+            .collect()
+    }
+
+    /// The job IDs each message lists, in order.
+    fn listed(messages: &[String], records: &[JobRecord]) -> Vec<Vec<String>> {
+        messages
+            .iter()
+            .map(|message| {
+                records
+                    .iter()
+                    .filter(|record| message.contains(&record.job.title))
+                    .map(|record| record.job.id.clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn a_long_digest_is_split_without_losing_or_repeating_jobs() {
+        let (telegram, chat) = service(None).await;
+        let records = records(150);
+
+        let (sent, result) = telegram.send_digest("42", &records, Urgency::Normal).await;
+
+        result.unwrap();
+        assert_eq!(sent.len(), records.len());
+        let messages = chat.lock().unwrap().messages.clone();
+        assert!(messages.len() > 1);
+        assert!(messages.iter().all(|message| message.len() <= MAX_MESSAGE_LEN));
+        let listed: Vec<String> = listed(&messages, &records).concat();
+        assert_eq!(listed, sent);
+    }
+
+    #[tokio::test]
+    async fn a_long_batch_is_split_and_stops_at_the_first_failed_part() {
+        let records = records(80);
+        let batch = NotificationBatch {
+            location: "Brampton, ON".into(),
+            jobs: records.iter().map(|record| record.job.clone()).collect(),
+        };
+
+        let (telegram, chat) = service(None).await;
+        let (sent, result) = telegram.send_batch("42", &batch, Urgency::Priority).await;
+        result.unwrap();
+        assert_eq!(sent.len(), batch.jobs.len());
+        let messages = chat.lock().unwrap().messages.clone();
+        assert!(messages.len() > 1);
+        assert!(messages.iter().all(|message| message.len() <= MAX_MESSAGE_LEN));
+        assert!(messages.iter().all(|message| message.starts_with("🚨 <b>Priority</b> <b>New Jobs in Brampton, ON</b>")));
+        assert_eq!(listed(&messages, &records).concat(), sent);
+
+        let (telegram, chat) = service(Some((1, 500))).await;
+        let (sent, result) = telegram.send_batch("42", &batch, Urgency::Normal).await;
+        assert!(result.is_err());
+        let messages = chat.lock().unwrap().messages.clone();
+        assert_eq!(listed(&messages, &records), [sent]);
+    }
+
+    #[tokio::test]
+    async fn a_partly_sent_digest_reports_only_the_jobs_that_went_out() {
+        let (telegram, chat) = service(Some((1, 500))).await;
+        let records = records(150);
+
+        let (sent, result) = telegram.send_digest("42", &records, Urgency::Normal).await;
+
+        assert!(result.is_err());
+        let messages = chat.lock().unwrap().messages.clone();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(listed(&messages, &records)[0], sent);
+        assert!(!sent.is_empty() && sent.len() < records.len());
+    }
+}
@@ -0,0 +1,316 @@
+use anyhow::{Context, Result};
+use async_channel::Sender;
+use crate::config::{AuthConfig, Config, Secret};
+use crate::model::Notification;
+use crate::utils::backoff_strategy;
+use log::{error, info, warn};
+use reqwest::Client;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+struct CachedToken {
+    value: Secret,
+    expires_at: Instant,
+    /// `refresh_margin_secs` before expiry, but never sooner than halfway,
+    /// so short-lived tokens aren't refreshed on every request.
+    refresh_at: Instant,
+}
+
+#[derive(Default)]
+struct TokenState {
+    cached: Option<CachedToken>,
+    consecutive_failures: u32,
+    next_attempt: Option<Instant>,
+    auth_broken: bool,
+}
+
+/// Hands out the Bearer token for the Amazon API. Without `[amazon.auth]`
+/// this is the static `api_token`; otherwise tokens are fetched from the
+/// token endpoint, cached until shortly before they expire and refreshed
+/// when the API rejects them.
+pub struct TokenService {
+    client: Client,
+    config: Config,
+    state: Mutex<TokenState>,
+    alerts: Sender<Notification>,
+}
+
+impl TokenService {
+    pub fn new(client: Client, config: Config, alerts: Sender<Notification>) -> Self {
+        TokenService {
+            client,
+            config,
+            state: Mutex::new(TokenState::default()),
+            alerts,
+        }
+    }
+
+    pub async fn token(&self) -> Result<Secret> {
+        let Some(auth) = &self.config.amazon.auth else {
+            return Ok(self.config.amazon.api_token.clone());
+        };
+
+        // Holding the lock across the refresh makes concurrent callers wait
+        // for a single token request instead of each starting their own
+        let mut state = self.state.lock().await;
+        if let Some(cached) = &state.cached
+            && Instant::now() < cached.refresh_at
+        {
+            return Ok(cached.value.clone());
+        }
+
+        if let Some(next_attempt) = state.next_attempt
+            && Instant::now() < next_attempt
+        {
+            return Err(anyhow::anyhow!("Token refresh is backing off after repeated failures"));
+        }
+
+        match self.refresh(auth).await {
+            Ok(cached) => {
+                info!(
+                    "Refreshed Amazon API token, valid for {:?}",
+                    cached.expires_at.saturating_duration_since(Instant::now())
+                );
+                let value = cached.value.clone();
+                state.cached = Some(cached);
+                state.consecutive_failures = 0;
+                state.next_attempt = None;
+                Ok(value)
+            }
+            Err(e) => {
+                let e = anyhow::anyhow!(self.config.redact(&format!("{:#}", e)));
+                let delay = backoff_strategy(
+                    state.consecutive_failures,
+                    self.config.rate_limiting.retry_base_ms,
+                    self.config.rate_limiting.retry_max_delay_ms,
+                );
+                state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+                state.next_attempt = Some(Instant::now() + delay);
+                warn!("Token refresh failed: {}. Next attempt in {:?}", e, delay);
+                self.escalate(&mut state, &format!("Token refresh failed: {}", e)).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Drops the cached token after the API rejected it. Returns `false` when
+    /// there is nothing to refresh, i.e. the static token itself is bad.
+    pub async fn invalidate(&self) -> bool {
+        if self.config.amazon.auth.is_none() {
+            return false;
+        }
+        self.state.lock().await.cached = None;
+        true
+    }
+
+    /// Reports that the API keeps rejecting our credentials.
+    pub async fn report_rejected(&self, reason: &str) {
+        let mut state = self.state.lock().await;
+        error!("Amazon API rejected credentials: {}", reason);
+        self.escalate(&mut state, &format!("Amazon API rejected credentials: {}", reason)).await;
+    }
+
+    /// Clears the broken flag once a request has gone through again.
+    pub async fn report_success(&self) {
+        let mut state = self.state.lock().await;
+        if state.auth_broken {
+            state.auth_broken = false;
+            info!("Amazon API authentication recovered");
+            self.notify("Amazon API authentication recovered".to_string());
+        }
+    }
+
+    async fn escalate(&self, state: &mut TokenState, reason: &str) {
+        // Alert once per outage rather than on every failed poll
+        if state.auth_broken {
+            return;
+        }
+        state.auth_broken = true;
+        self.notify(format!(
+            "Amazon API auth is broken, polling will keep failing until it is fixed.\n{}",
+            reason
+        ));
+    }
+
+    fn notify(&self, text: String) {
+        if let Err(e) = self.alerts.try_send(Notification::Alert(text)) {
+            error!("Failed to queue auth alert: {}", e);
+        }
+    }
+
+    async fn refresh(&self, auth: &AuthConfig) -> Result<CachedToken> {
+        let mut form = auth.form.clone();
+        if !auth.refresh_token.expose().is_empty() {
+            form.insert("refresh_token".into(), auth.refresh_token.expose().to_string());
+        }
+
+        let response = self.client
+            .post(&auth.token_url)
+            .form(&form)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await?;
+            return Err(anyhow::anyhow!("Token endpoint returned {}: {}", status, body));
+        }
+
+        let body: serde_json::Value = response.json().await
+            .with_context(|| "Token endpoint returned invalid JSON")?;
+        let token = body
+            .get(&auth.token_field)
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.is_empty())
+            .with_context(|| format!("Token response has no `{}` field", auth.token_field))?;
+        let ttl = body
+            .get(&auth.expires_in_field)
+            .and_then(|v| v.as_u64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
+            .unwrap_or(auth.default_ttl_secs);
+
+        let value = Secret::from(token);
+        self.config.add_runtime_secret(value.clone());
+        let now = Instant::now();
+        let margin = auth.refresh_margin_secs.min(ttl / 2);
+        Ok(CachedToken {
+            value,
+            expires_at: now + Duration::from_secs(ttl),
+            refresh_at: now + Duration::from_secs(ttl - margin),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_channel::{bounded, Receiver};
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct Endpoint {
+        requests: usize,
+        failing: bool,
+        expires_in: u64,
+    }
+
+    /// Hands out `token-1`, `token-2`, ... or a 500 while failing.
+    async fn issue(State(endpoint): State<Arc<std::sync::Mutex<Endpoint>>>) -> (StatusCode, Json<serde_json::Value>) {
+        let mut endpoint = endpoint.lock().unwrap();
+        endpoint.requests += 1;
+        if endpoint.failing {
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({})));
+        }
+        let body = serde_json::json!({
+            "access_token": format!("token-{}", endpoint.requests),
+            "expires_in": endpoint.expires_in,
+        });
+        (StatusCode::OK, Json(body))
+    }
+
+    async fn service(expires_in: u64) -> (TokenService, Arc<std::sync::Mutex<Endpoint>>, Receiver<Notification>) {
+        let endpoint = Arc::new(std::sync::Mutex::new(Endpoint { expires_in, ..Endpoint::default() }));
+        let router = Router::new().route("/token", post(issue)).with_state(endpoint.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/token", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let config: Config = toml::from_str(&format!(
+            r#"
+[amazon]
+api_url = "https://example.com/graphql"
+country = "Canada"
+locale = "en-US"
+page_size = 100
+auth = {{ token_url = "{url}", refresh_margin_secs = 60 }}
+
+[telegram]
+bot_token = "123:abc"
+chat_id = "42"
+
+[persistence]
+seen_jobs_file = "seen_jobs.txt"
+persist_interval_secs = 300
+
+[rate_limiting]
+requests_per_second = 2
+retry_base_ms = 50
+retry_max_delay_ms = 50
+max_retries = 5
+"#
+        ))
+        .unwrap();
+        let (alerts, received) = bounded(10);
+        (TokenService::new(Client::new(), config, alerts), endpoint, received)
+    }
+
+    fn alerts(received: &Receiver<Notification>) -> Vec<String> {
+        std::iter::from_fn(|| received.try_recv().ok())
+            .map(|notification| match notification {
+                Notification::Alert(text) => text,
+                _ => panic!("expected an alert"),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn tokens_are_cached_until_the_refresh_margin() {
+        let (service, endpoint, _) = service(3600).await;
+        assert_eq!(service.token().await.unwrap().expose(), "token-1");
+        assert_eq!(service.token().await.unwrap().expose(), "token-1");
+        assert_eq!(endpoint.lock().unwrap().requests, 1);
+    }
+
+    #[tokio::test]
+    async fn short_lived_tokens_are_not_refreshed_on_every_request() {
+        // Lives for less than the 60s margin, so is used for half its life
+        let (service, endpoint, _) = service(30).await;
+        service.token().await.unwrap();
+        service.token().await.unwrap();
+        assert_eq!(endpoint.lock().unwrap().requests, 1);
+    }
+
+    #[tokio::test]
+    async fn a_rejected_token_is_replaced() {
+        let (service, endpoint, _) = service(3600).await;
+        assert_eq!(service.token().await.unwrap().expose(), "token-1");
+        assert!(service.invalidate().await);
+        assert_eq!(service.token().await.unwrap().expose(), "token-2");
+        assert_eq!(endpoint.lock().unwrap().requests, 2);
+    }
+
+    #[tokio::test]
+    async fn fetched_tokens_are_redacted() {
+        let (service, _, _) = service(3600).await;
+        service.token().await.unwrap();
+        let config = service.config.clone();
+        assert_eq!(config.redact("Bearer token-1"), "Bearer [REDACTED]");
+    }
+
+    #[tokio::test]
+    async fn failures_back_off_and_alert_once_per_outage() {
+        let (service, endpoint, received) = service(3600).await;
+        endpoint.lock().unwrap().failing = true;
+
+        assert!(service.token().await.is_err());
+        let error = service.token().await.unwrap_err();
+        assert!(error.to_string().contains("backing off"), "{}", error);
+        assert_eq!(endpoint.lock().unwrap().requests, 1);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(service.token().await.is_err());
+        assert_eq!(endpoint.lock().unwrap().requests, 2);
+        let sent = alerts(&received);
+        assert_eq!(sent.len(), 1, "{:?}", sent);
+        assert!(sent[0].contains("Token refresh failed"));
+
+        endpoint.lock().unwrap().failing = false;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(service.token().await.unwrap().expose(), "token-3");
+        service.report_success().await;
+        assert_eq!(alerts(&received), ["Amazon API authentication recovered"]);
+    }
+}
@@ -1,20 +1,73 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveTime, TimeZone, Weekday};
+use reqwest::Certificate;
+use std::fs;
 use std::time::Duration;
 
 pub fn backoff_strategy(attempt: u32, base_delay_ms: u64, max_delay_ms: u64) -> Duration {
-    let exponent = attempt as u32;
+    let exponent = attempt;
     let delay = base_delay_ms.saturating_mul(2u64.saturating_pow(exponent));
     Duration::from_millis(delay.min(max_delay_ms))
 }
 
+/// Parses `"HH:MM-HH:MM"`. The end may be earlier than the start for ranges
+/// that wrap past midnight.
+pub fn parse_time_range(range: &str) -> Option<(NaiveTime, NaiveTime)> {
+    let (start, end) = range.split_once('-')?;
+    let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?;
+    let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?;
+    Some((start, end))
+}
+
+pub fn time_in_range(time: NaiveTime, (start, end): (NaiveTime, NaiveTime)) -> bool {
+    if start <= end {
+        start <= time && time < end
+    } else {
+        time >= start || time < end
+    }
+}
+
+/// The next `day` at `time` in `now`'s time zone strictly after `now`. A
+/// time that doesn't exist that day (DST gap) moves to the first valid one
+/// after it.
+pub fn next_weekly<Tz: TimeZone>(now: DateTime<Tz>, day: Weekday, time: NaiveTime) -> DateTime<Tz> {
+    let zone = now.timezone();
+    let today = now.date_naive();
+    let days_ahead = (day.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64).rem_euclid(7);
+    let mut date = today + ChronoDuration::days(days_ahead);
+    loop {
+        let candidate = date.and_time(time);
+        let local = zone
+            .from_local_datetime(&candidate)
+            .earliest()
+            .or_else(|| zone.from_local_datetime(&(candidate + ChronoDuration::hours(1))).earliest());
+        if let Some(local) = local
+            && local > now
+        {
+            return local;
+        }
+        date += ChronoDuration::days(7);
+    }
+}
+
 pub fn escape_html(text: &str) -> String {
     text.replace('&', "&amp;")
         .replace('<', "&lt;")
         .replace('>', "&gt;")
 }
 
+pub fn format_pay(min: Option<f64>, max: Option<f64>) -> String {
+    match (min, max) {
+        (Some(min), Some(max)) => format!("${:.2}-${:.2}/hr", min, max),
+        (Some(pay), None) | (None, Some(pay)) => format!("${:.2}/hr", pay),
+        (None, None) => "n/a".to_string(),
+    }
+}
+
 pub fn humanize_job_type(raw: &str) -> String {
     let parts: Vec<String> = raw
         .split(';')
+        .filter(|part| !part.trim().is_empty())
         .map(|part| {
             let part = part.trim().to_uppercase();
             match part.as_str() {
@@ -44,4 +97,68 @@ pub fn humanize_job_type(raw: &str) -> String {
     } else {
         format!("{} time", parts.join("/ "))
     }
-}
\ No newline at end of file
+}
+
+/// Reads a PEM file of one or more CA certificates, trusted on top of the
+/// built-in roots.
+pub fn load_ca_bundle(path: &str) -> Result<Vec<Certificate>> {
+    let pem = fs::read(path).with_context(|| format!("Failed to read CA bundle: {}", path))?;
+    let certificates = Certificate::from_pem_bundle(&pem)
+        .with_context(|| format!("Failed to parse CA bundle: {}", path))?;
+    if certificates.is_empty() {
+        anyhow::bail!("CA bundle {} holds no certificates", path);
+    }
+    Ok(certificates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::America::Toronto;
+
+    fn at(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn toronto(month: u32, day: u32, hour: u32, minute: u32) -> DateTime<chrono_tz::Tz> {
+        Toronto.with_ymd_and_hms(2024, month, day, hour, minute, 0).unwrap()
+    }
+
+    fn utc(month: u32, day: u32, hour: u32, minute: u32) -> chrono::NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2024, month, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn ranges_can_wrap_past_midnight() {
+        let night = (at(22, 0), at(7, 0));
+        assert!(time_in_range(at(22, 0), night));
+        assert!(time_in_range(at(23, 30), night));
+        assert!(time_in_range(at(3, 0), night));
+        assert!(!time_in_range(at(7, 0), night));
+        assert!(!time_in_range(at(12, 0), night));
+
+        let day = (at(9, 0), at(17, 0));
+        assert!(time_in_range(at(9, 0), day));
+        assert!(!time_in_range(at(17, 0), day));
+        assert!(!time_in_range(at(23, 0), day));
+    }
+
+    #[test]
+    fn next_weekly_is_strictly_after_now() {
+        // A Monday
+        let now = toronto(6, 10, 9, 0);
+        assert_eq!(next_weekly(now, Weekday::Mon, at(9, 0)), toronto(6, 17, 9, 0));
+        assert_eq!(next_weekly(now, Weekday::Mon, at(9, 1)), toronto(6, 10, 9, 1));
+        assert_eq!(next_weekly(now, Weekday::Sun, at(8, 0)), toronto(6, 16, 8, 0));
+    }
+
+    #[test]
+    fn next_weekly_moves_past_a_dst_gap_and_takes_the_first_repeat() {
+        // 02:30 doesn't exist on 2024-03-10 and 01:30 happens twice on 2024-11-03
+        let after_gap = next_weekly(toronto(3, 9, 12, 0), Weekday::Sun, at(2, 30));
+        assert_eq!(after_gap.naive_utc(), utc(3, 10, 7, 30));
+
+        let repeated = next_weekly(toronto(11, 2, 12, 0), Weekday::Sun, at(1, 30));
+        assert_eq!(repeated.naive_utc(), utc(11, 3, 5, 30));
+    }
+}
@@ -0,0 +1,302 @@
+//! Local stand-ins for Amazon's GraphQL endpoint and the Telegram Bot API
+//! serving recorded fixtures, and a way to run the monitor against them.
+
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde_json::Value;
+use std::fs;
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use tokio::net::TcpListener;
+
+pub const BOT_TOKEN: &str = "123:test-bot-token";
+pub const OPERATOR_CHAT: &str = "42";
+
+/// How long to wait for expected messages before failing.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(15);
+/// How long nothing more may arrive once the expected messages are in.
+const QUIET_PERIOD: Duration = Duration::from_millis(1500);
+
+fn fixture(path: &str) -> String {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), path);
+    fs::read_to_string(&path).unwrap_or_else(|e| panic!("Failed to read fixture {}: {}", path, e))
+}
+
+async fn serve(router: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock server");
+    let url = format!("http://{}", listener.local_addr().expect("mock server address"));
+    tokio::spawn(async move { axum::serve(listener, router).await.expect("mock server") });
+    url
+}
+
+/// One response from the GraphQL stand-in.
+#[derive(Clone)]
+pub struct Reply {
+    status: StatusCode,
+    body: String,
+    retry_after: Option<u64>,
+}
+
+impl Reply {
+    /// A 200 with `tests/fixtures/amazon/<name>.json`.
+    pub fn page(name: &str) -> Self {
+        Reply::status(200, name)
+    }
+
+    pub fn status(status: u16, name: &str) -> Self {
+        Reply {
+            status: StatusCode::from_u16(status).expect("valid status"),
+            body: fixture(&format!("amazon/{}.json", name)),
+            retry_after: None,
+        }
+    }
+
+    pub fn retry_after(mut self, secs: u64) -> Self {
+        self.retry_after = Some(secs);
+        self
+    }
+}
+
+impl IntoResponse for Reply {
+    fn into_response(self) -> Response {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", "application/json".parse().unwrap());
+        if let Some(secs) = self.retry_after {
+            headers.insert("retry-after", secs.into());
+        }
+        (self.status, headers, self.body).into_response()
+    }
+}
+
+#[derive(Default)]
+struct AmazonState {
+    script: Vec<Reply>,
+    requests: Vec<Value>,
+}
+
+/// Answers each search with the next scripted reply, repeating the last one
+/// once the script runs out.
+pub struct MockAmazon {
+    pub url: String,
+    state: Arc<Mutex<AmazonState>>,
+}
+
+impl MockAmazon {
+    pub async fn start(script: Vec<Reply>) -> Self {
+        assert!(!script.is_empty(), "the script needs at least one reply");
+        let state = Arc::new(Mutex::new(AmazonState { script, requests: Vec::new() }));
+        let router = Router::new()
+            .route("/graphql", post(search))
+            .with_state(state.clone());
+        let url = format!("{}/graphql", serve(router).await);
+        MockAmazon { url, state }
+    }
+
+    /// The GraphQL payloads received so far.
+    pub fn requests(&self) -> Vec<Value> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+async fn search(State(state): State<Arc<Mutex<AmazonState>>>, Json(payload): Json<Value>) -> Reply {
+    let mut state = state.lock().unwrap();
+    let index = state.requests.len().min(state.script.len() - 1);
+    state.requests.push(payload);
+    state.script[index].clone()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentMessage {
+    pub chat_id: String,
+    pub text: String,
+    pub silent: bool,
+}
+
+impl SentMessage {
+    /// Titles of the jobs listed, in order.
+    pub fn job_titles(&self) -> Vec<&str> {
+        self.text
+            .lines()
+            .skip(1)
+            .filter_map(|line| line.strip_prefix("<b>")?.strip_suffix("</b>"))
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct TelegramState {
+    delivered: Vec<SentMessage>,
+    attempts: usize,
+    /// How many of the next `sendMessage` calls get a 429.
+    failures: usize,
+    /// Chats that blocked the bot and get a 403.
+    blocked: Vec<String>,
+}
+
+/// Accepts `getMe` and `sendMessage` for [`BOT_TOKEN`] and records every
+/// message it accepted.
+pub struct MockTelegram {
+    pub url: String,
+    state: Arc<Mutex<TelegramState>>,
+}
+
+impl MockTelegram {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(TelegramState::default()));
+        let router = Router::new()
+            .route("/{bot}/getMe", get(get_me))
+            .route("/{bot}/sendMessage", post(send_message))
+            .with_state(state.clone());
+        MockTelegram { url: serve(router).await, state }
+    }
+
+    /// Throttles the next `count` messages the way Telegram does.
+    pub fn fail_next(&self, count: usize) {
+        self.state.lock().unwrap().failures = count;
+    }
+
+    /// Refuses every message to `chat_id` the way Telegram does once a user
+    /// blocked the bot.
+    pub fn block_chat(&self, chat_id: &str) {
+        self.state.lock().unwrap().blocked.push(chat_id.to_string());
+    }
+
+    /// Every `sendMessage` call, throttled and refused ones included.
+    pub fn attempts(&self) -> usize {
+        self.state.lock().unwrap().attempts
+    }
+
+    /// Waits for `count` messages, then for a quiet period so stray extra
+    /// messages show up too, and returns everything delivered.
+    pub async fn expect_messages(&self, count: usize) -> Vec<SentMessage> {
+        let deadline = Instant::now() + DELIVERY_TIMEOUT;
+        while self.state.lock().unwrap().delivered.len() < count {
+            assert!(
+                Instant::now() < deadline,
+                "expected {} messages, got {:#?}",
+                count,
+                self.state.lock().unwrap().delivered
+            );
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        tokio::time::sleep(QUIET_PERIOD).await;
+        self.state.lock().unwrap().delivered.clone()
+    }
+}
+
+fn bot_reply(status: StatusCode, name: &str) -> Response {
+    let body = fixture(&format!("telegram/{}.json", name));
+    (status, [("content-type", "application/json")], body).into_response()
+}
+
+async fn get_me(Path(bot): Path<String>) -> Response {
+    if bot != format!("bot{}", BOT_TOKEN) {
+        return bot_reply(StatusCode::UNAUTHORIZED, "unauthorized");
+    }
+    bot_reply(StatusCode::OK, "get_me")
+}
+
+async fn send_message(
+    State(state): State<Arc<Mutex<TelegramState>>>,
+    Path(bot): Path<String>,
+    Json(payload): Json<Value>,
+) -> Response {
+    if bot != format!("bot{}", BOT_TOKEN) {
+        return bot_reply(StatusCode::UNAUTHORIZED, "unauthorized");
+    }
+    let mut state = state.lock().unwrap();
+    state.attempts += 1;
+    if state.failures > 0 {
+        state.failures -= 1;
+        return bot_reply(StatusCode::TOO_MANY_REQUESTS, "too_many_requests");
+    }
+    let chat_id = payload["chat_id"].as_str().expect("chat_id is a string").to_string();
+    if state.blocked.contains(&chat_id) {
+        return bot_reply(StatusCode::FORBIDDEN, "blocked");
+    }
+    state.delivered.push(SentMessage {
+        chat_id,
+        text: payload["text"].as_str().expect("text is a string").to_string(),
+        silent: payload["disable_notification"].as_bool().unwrap_or(false),
+    });
+    bot_reply(StatusCode::OK, "send_message")
+}
+
+/// The monitor binary running in a scratch directory against the stand-ins.
+/// Killed on drop; its log is printed if the test failed.
+pub struct Monitor {
+    child: Child,
+    dir: TempDir,
+}
+
+impl Monitor {
+    /// `extra` is appended to the generated config.toml, e.g. subscribers.
+    pub fn start(amazon: &MockAmazon, telegram: &MockTelegram, extra: &str) -> Self {
+        let dir = TempDir::new().expect("create scratch directory");
+        let config = format!(
+            r#"[amazon]
+api_url = "{amazon}"
+api_token = "test-api-token"
+country = "Canada"
+locale = "en-US"
+page_size = 100
+
+[telegram]
+api_url = "{telegram}"
+bot_token = "{BOT_TOKEN}"
+chat_id = "{OPERATOR_CHAT}"
+coalesce_window_ms = 200
+
+[persistence]
+seen_jobs_file = "seen_jobs.txt"
+persist_interval_secs = 300
+
+[rate_limiting]
+requests_per_second = 10
+retry_base_ms = 50
+retry_max_delay_ms = 200
+max_retries = 3
+
+[scheduler]
+max_interval_ms = 200
+
+[server]
+enabled = false
+{extra}"#,
+            amazon = amazon.url,
+            telegram = telegram.url,
+        );
+        fs::write(dir.path().join("config.toml"), config).expect("write config.toml");
+        let log = fs::File::create(dir.path().join("monitor.log")).expect("create monitor.log");
+
+        let mut command = Command::new(env!("CARGO_BIN_EXE_job-log-moduler"));
+        for (name, _) in std::env::vars().filter(|(name, _)| name.starts_with("JOBLOG__")) {
+            command.env_remove(name);
+        }
+        let child = command
+            .current_dir(dir.path())
+            .env("RUST_LOG", "info")
+            .stdout(log.try_clone().expect("clone log handle"))
+            .stderr(log)
+            .stdin(Stdio::null())
+            .spawn()
+            .expect("start the monitor");
+        Monitor { child, dir }
+    }
+}
+
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        if std::thread::panicking() {
+            let log = fs::read_to_string(self.dir.path().join("monitor.log")).unwrap_or_default();
+            eprintln!("--- monitor log ---\n{}", log);
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! Runs the monitor against recorded Amazon and Telegram responses and checks
+//! exactly what gets delivered.
+
+mod common;
+
+use common::{MockAmazon, MockTelegram, Monitor, Reply, OPERATOR_CHAT};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_jobs_are_delivered_once() {
+    let amazon = MockAmazon::start(vec![Reply::page("two_jobs")]).await;
+    let telegram = MockTelegram::start().await;
+    let _monitor = Monitor::start(&amazon, &telegram, "");
+
+    let messages = telegram.expect_messages(1).await;
+    assert_eq!(messages.len(), 1, "{:#?}", messages);
+    assert_eq!(messages[0].chat_id, OPERATOR_CHAT);
+    assert!(!messages[0].silent);
+    assert_eq!(
+        messages[0].text,
+        "<b>New Jobs in Brampton, ON</b>\n\
+         ═══════════════════\n\
+         <b>Warehouse Associate</b>\n\
+         - Type: Full time\n\
+         - Shifts: 4\n\
+         - Pay: $19.50-$21.00/hr\n\
+         ═══════════════════\n\
+         <b>Sortation Associate</b>\n\
+         - Type: Part time\n\
+         - Shifts: 2\n\
+         - Pay: $18.25-$18.25/hr\n\
+         ═══════════════════\n"
+    );
+    // The same page keeps coming back without anything being sent again
+    assert!(amazon.requests().len() > 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_the_first_page_is_read() {
+    let amazon = MockAmazon::start(vec![Reply::page("first_page_of_two")]).await;
+    let telegram = MockTelegram::start().await;
+    let _monitor = Monitor::start(&amazon, &telegram, "");
+
+    let messages = telegram.expect_messages(1).await;
+    assert_eq!(messages.len(), 1, "{:#?}", messages);
+    assert_eq!(
+        messages[0].job_titles(),
+        ["Delivery Station Associate", "Fulfillment Center Associate"]
+    );
+    // `page_size` bounds a poll; the `nextToken` is never followed
+    for request in amazon.requests() {
+        assert!(request["variables"]["searchJobRequest"].get("nextToken").is_none());
+        assert_eq!(request["variables"]["searchJobRequest"]["pageSize"], 100);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn recovers_from_server_errors_and_throttling() {
+    let amazon = MockAmazon::start(vec![
+        Reply::status(500, "internal_error"),
+        Reply::status(429, "too_many_requests").retry_after(1),
+        Reply::page("two_jobs"),
+    ])
+    .await;
+    let telegram = MockTelegram::start().await;
+    let _monitor = Monitor::start(&amazon, &telegram, "");
+
+    let messages = telegram.expect_messages(1).await;
+    assert_eq!(messages.len(), 1, "{:#?}", messages);
+    assert_eq!(messages[0].job_titles(), ["Warehouse Associate", "Sortation Associate"]);
+    assert!(amazon.requests().len() >= 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn graphql_errors_deliver_nothing() {
+    let amazon = MockAmazon::start(vec![
+        Reply::page("graphql_error"),
+        Reply::page("graphql_error"),
+        Reply::page("two_jobs"),
+    ])
+    .await;
+    let telegram = MockTelegram::start().await;
+    let _monitor = Monitor::start(&amazon, &telegram, "");
+
+    let messages = telegram.expect_messages(1).await;
+    assert_eq!(messages.len(), 1, "{:#?}", messages);
+    assert_eq!(messages[0].job_titles(), ["Warehouse Associate", "Sortation Associate"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn malformed_cards_are_skipped() {
+    let amazon = MockAmazon::start(vec![Reply::page("malformed_cards")]).await;
+    let telegram = MockTelegram::start().await;
+    let _monitor = Monitor::start(&amazon, &telegram, "");
+
+    let messages = telegram.expect_messages(1).await;
+    assert_eq!(messages.len(), 1, "{:#?}", messages);
+    assert_eq!(messages[0].job_titles(), ["Inbound Associate"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failed_deliveries_are_retried() {
+    let amazon = MockAmazon::start(vec![Reply::page("two_jobs")]).await;
+    let telegram = MockTelegram::start().await;
+    telegram.fail_next(1);
+    let _monitor = Monitor::start(&amazon, &telegram, "");
+
+    let messages = telegram.expect_messages(1).await;
+    assert_eq!(messages.len(), 1, "{:#?}", messages);
+    assert_eq!(messages[0].job_titles(), ["Warehouse Associate", "Sortation Associate"]);
+    assert_eq!(telegram.attempts(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn subscribers_get_only_matching_jobs() {
+    let amazon = MockAmazon::start(vec![Reply::page("two_jobs")]).await;
+    let telegram = MockTelegram::start().await;
+    let _monitor = Monitor::start(
+        &amazon,
+        &telegram,
+        r#"
+[[telegram.subscribers]]
+name = "full-time"
+chat_id = "100"
+filter = { type = "full" }
+
+[[telegram.subscribers]]
+name = "everything"
+chat_id = "200"
+"#,
+    );
+
+    let mut messages = telegram.expect_messages(2).await;
+    assert_eq!(messages.len(), 2, "{:#?}", messages);
+    messages.sort_by(|a, b| a.chat_id.cmp(&b.chat_id));
+    assert_eq!(messages[0].chat_id, "100");
+    assert_eq!(messages[0].job_titles(), ["Warehouse Associate"]);
+    assert_eq!(messages[1].chat_id, "200");
+    assert_eq!(messages[1].job_titles(), ["Warehouse Associate", "Sortation Associate"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_retry_skips_subscribers_that_got_the_jobs() {
+    let amazon = MockAmazon::start(vec![Reply::page("two_jobs")]).await;
+    let telegram = MockTelegram::start().await;
+    telegram.fail_next(1);
+    let _monitor = Monitor::start(
+        &amazon,
+        &telegram,
+        r#"
+[[telegram.subscribers]]
+name = "first"
+chat_id = "100"
+
+[[telegram.subscribers]]
+name = "second"
+chat_id = "200"
+"#,
+    );
+
+    let mut messages = telegram.expect_messages(2).await;
+    assert_eq!(messages.len(), 2, "{:#?}", messages);
+    messages.sort_by(|a, b| a.chat_id.cmp(&b.chat_id));
+    assert_eq!(messages[0].chat_id, "100");
+    assert_eq!(messages[1].chat_id, "200");
+    assert_eq!(telegram.attempts(), 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_chat_that_blocked_the_bot_is_not_retried() {
+    let amazon = MockAmazon::start(vec![Reply::page("two_jobs")]).await;
+    let telegram = MockTelegram::start().await;
+    telegram.block_chat("200");
+    let _monitor = Monitor::start(
+        &amazon,
+        &telegram,
+        r#"
+[[telegram.subscribers]]
+name = "first"
+chat_id = "100"
+
+[[telegram.subscribers]]
+name = "blocked"
+chat_id = "200"
+"#,
+    );
+
+    let messages = telegram.expect_messages(1).await;
+    assert_eq!(messages.len(), 1, "{:#?}", messages);
+    assert_eq!(messages[0].chat_id, "100");
+    assert_eq!(telegram.attempts(), 2);
+}